@@ -0,0 +1,139 @@
+use std::{
+    fs::File,
+    io::{Error, ErrorKind, Read, Seek, SeekFrom, Take},
+    path::{Component, Path, PathBuf},
+};
+
+use flate2::read::DeflateDecoder;
+use zip::{CompressionMethod, ZipArchive};
+
+/// Extension of zip archives
+pub const ZIP_EXTENSION: &str = ".zip";
+
+/// Returns `true` if the file name has the zip extension
+pub fn is_zip(filename: &Path) -> bool {
+    filename.to_str().unwrap().ends_with(ZIP_EXTENSION)
+}
+
+/// Splits a path of an archive member like `data.zip/dir/a.csv` into the archive path
+/// and the member name
+pub fn split_member(filename: &Path) -> Option<(&Path, String)> {
+    let archive = filename
+        .ancestors()
+        .skip(1)
+        .find(|archive| is_zip(archive) && archive.is_file())?;
+    let name = filename.strip_prefix(archive).unwrap().to_str().unwrap();
+    Some((archive, name.replace(std::path::MAIN_SEPARATOR, "/")))
+}
+
+/// Returns paths of archive members which names are accepted by `is_csv` and names of such
+/// members which are absolute or lead outside of the archive
+pub fn members(
+    archive: &Path,
+    is_csv: impl Fn(&str) -> bool,
+) -> std::io::Result<(Vec<PathBuf>, Vec<String>)> {
+    let mut zip = ZipArchive::new(File::open(archive)?)?;
+    let mut members = vec![];
+    let mut outside = vec![];
+    for index in 0..zip.len() {
+        let member = zip.by_index_raw(index)?;
+        if !member.is_file() || !is_csv(member.name()) {
+            continue;
+        }
+        let enclosed = member.enclosed_name().filter(|name| {
+            name.components()
+                .all(|component| matches!(component, Component::Normal(_)))
+        });
+        match enclosed {
+            Some(name) => members.push(archive.join(name)),
+            None => outside.push(member.name().to_string()),
+        }
+    }
+    Ok((members, outside))
+}
+
+/// Returns the compressed size of an archive member
+pub fn member_size(archive: &Path, name: &str) -> std::io::Result<u64> {
+    let mut zip = ZipArchive::new(File::open(archive)?)?;
+    let index = member_index(&zip, name)?;
+    Ok(zip.by_index_raw(index)?.compressed_size())
+}
+
+fn member_index(zip: &ZipArchive<File>, name: &str) -> std::io::Result<usize> {
+    zip.index_for_name(name)
+        .ok_or_else(|| Error::new(ErrorKind::NotFound, format!("{name} is not in the archive")))
+}
+
+/// Decompressing reader of a stored or deflated archive member
+enum MemberReader {
+    Stored(Take<File>),
+    Deflated(DeflateDecoder<Take<File>>),
+}
+
+/// Archive member read directly from the archive file
+pub struct ZipMember {
+    reader: MemberReader,
+    deflated: bool,
+    /// Offset of the member data in the archive
+    start: u64,
+    /// Compressed size of the member
+    size: u64,
+}
+
+impl ZipMember {
+    /// Opens a member of the archive
+    pub fn open(archive: &Path, name: &str) -> std::io::Result<Self> {
+        let mut zip = ZipArchive::new(File::open(archive)?)?;
+        let index = member_index(&zip, name)?;
+        let member = zip.by_index_raw(index)?;
+        // Data is decompressed here, so the zip crate is built without codecs
+        let method = member.compression();
+        let deflated = if method == CompressionMethod::DEFLATE {
+            true
+        } else if method == CompressionMethod::STORE {
+            false
+        } else {
+            return Err(Error::new(
+                ErrorKind::Unsupported,
+                format!("{name} is compressed with unsupported method {method}"),
+            ));
+        };
+        let (start, size) = (member.data_start(), member.compressed_size());
+        drop(member);
+        ZipMember::new(zip.into_inner(), deflated, start, size)
+    }
+
+    fn new(mut file: File, deflated: bool, start: u64, size: u64) -> std::io::Result<Self> {
+        file.seek(SeekFrom::Start(start))?;
+        let data = file.take(size);
+        let reader = if deflated {
+            MemberReader::Deflated(DeflateDecoder::new(data))
+        } else {
+            MemberReader::Stored(data)
+        };
+        Ok(Self {
+            reader,
+            deflated,
+            start,
+            size,
+        })
+    }
+
+    /// Rewinds to the beginning of the member
+    pub fn rewind(self) -> std::io::Result<Self> {
+        let data = match self.reader {
+            MemberReader::Stored(data) => data,
+            MemberReader::Deflated(decoder) => decoder.into_inner(),
+        };
+        ZipMember::new(data.into_inner(), self.deflated, self.start, self.size)
+    }
+}
+
+impl Read for ZipMember {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match &mut self.reader {
+            MemberReader::Stored(data) => data.read(buf),
+            MemberReader::Deflated(decoder) => decoder.read(buf),
+        }
+    }
+}
@@ -0,0 +1,56 @@
+use anyhow::{Result, anyhow};
+use parquet::basic::{BrotliLevel, Compression, GzipLevel, ZstdLevel};
+
+/// Parses a compression codec specification in the form of `CODEC[:LEVEL]`, e.g. `zstd:9`.
+/// Codecs which support levels use the parquet default level when it's omitted.
+pub fn parse_compression(spec: &str) -> Result<Compression> {
+    let (codec, level) = match spec.split_once(':') {
+        Some((codec, level)) => {
+            let level = level
+                .parse::<i32>()
+                .map_err(|_| anyhow!("Invalid compression level `{level}'"))?;
+            (codec, Some(level))
+        }
+        None => (spec, None),
+    };
+    let codec = codec.to_ascii_lowercase();
+
+    let compression = match codec.as_str() {
+        "uncompressed" | "none" => Compression::UNCOMPRESSED,
+        "snappy" => Compression::SNAPPY,
+        "lz4_raw" => Compression::LZ4_RAW,
+        "gzip" => Compression::GZIP(match level {
+            // Parquet accepts 10, which is not a standard gzip level
+            Some(level @ 10..) => return Err(anyhow!("Invalid gzip level {level}, expected 0-9")),
+            Some(level) => GzipLevel::try_new(unsigned_level(level)?)?,
+            None => GzipLevel::default(),
+        }),
+        "brotli" => Compression::BROTLI(match level {
+            Some(level) => BrotliLevel::try_new(unsigned_level(level)?)?,
+            None => BrotliLevel::default(),
+        }),
+        "zstd" => Compression::ZSTD(match level {
+            Some(level) => ZstdLevel::try_new(level)?,
+            None => ZstdLevel::default(),
+        }),
+        _ => {
+            return Err(anyhow!(
+                "Unsupported compression `{codec}'. Expected one of: \
+                snappy, gzip, zstd, lz4_raw, brotli, uncompressed"
+            ));
+        }
+    };
+    if level.is_some()
+        && matches!(
+            compression,
+            Compression::UNCOMPRESSED | Compression::SNAPPY | Compression::LZ4_RAW
+        )
+    {
+        return Err(anyhow!("Compression `{codec}' doesn't support levels"));
+    }
+    Ok(compression)
+}
+
+fn unsigned_level(level: i32) -> Result<u32> {
+    u32::try_from(level).map_err(|_| anyhow!("Compression level must be non-negative"))
+}
@@ -0,0 +1,177 @@
+use std::sync::Arc;
+
+use anyhow::{Result, anyhow};
+use arrow_array::{Array, ArrayRef, RecordBatch, StringArray};
+use arrow_cast::{CastOptions, cast_with_options};
+use arrow_schema::{DataType, Field, Fields, Schema, SchemaRef, TimeUnit};
+
+use crate::{
+    decimal::{DecimalExcess, convert_decimal_column},
+    format::NullValues,
+    schema::ColumnError,
+    timestamp::{TimestampSettings, convert_epoch_column, convert_format_column},
+};
+
+/// Conversion of a column which is read as strings and parsed by us rather than by arrow-csv
+enum Conversion {
+    /// Arrow cast
+    Cast,
+    /// Decimal with a custom excess policy
+    Decimal,
+    /// Timestamp or date with a `strftime` format
+    Format(String),
+    /// Time since the Unix epoch
+    Epoch(TimeUnit),
+}
+
+/// Column conversion
+struct ColumnConversion {
+    /// Column specific null values
+    null_values: Option<Vec<String>>,
+    conversion: Conversion,
+}
+
+/// Converts columns which arrow-csv can't parse the way we need
+pub struct Converter {
+    schema: SchemaRef,
+    read_schema: SchemaRef,
+    conversions: Vec<Option<ColumnConversion>>,
+    decimal_excess: DecimalExcess,
+}
+
+impl Converter {
+    /// Creates a converter for the target schema
+    pub fn new(
+        schema: SchemaRef,
+        timestamps: &TimestampSettings,
+        null_values: &NullValues,
+        decimal_excess: DecimalExcess,
+    ) -> Self {
+        let conversions: Vec<Option<ColumnConversion>> = schema
+            .fields()
+            .iter()
+            .map(|field| {
+                let name = field.name();
+                let null_values = null_values.column(name).map(<[String]>::to_vec);
+                let temporal = field.data_type().is_temporal();
+                let conversion = if let Some((_, format)) = timestamps
+                    .formats
+                    .iter()
+                    .find(|(c, _)| temporal && c == name)
+                {
+                    Conversion::Format(format.clone())
+                } else if let Some((_, unit)) = timestamps
+                    .epochs
+                    .iter()
+                    .find(|(c, _)| temporal && c == name)
+                {
+                    Conversion::Epoch(*unit)
+                } else if matches!(
+                    field.data_type(),
+                    DataType::Decimal128(_, _) | DataType::Decimal256(_, _)
+                ) {
+                    Conversion::Decimal
+                } else if null_values.is_some() {
+                    Conversion::Cast
+                } else {
+                    return None;
+                };
+                Some(ColumnConversion {
+                    null_values,
+                    conversion,
+                })
+            })
+            .collect();
+        let read_fields: Fields = schema
+            .fields()
+            .iter()
+            .zip(&conversions)
+            .map(|(field, conversion)| match conversion {
+                Some(_) => Field::new(field.name(), DataType::Utf8, field.is_nullable()),
+                None => field.as_ref().clone(),
+            })
+            .collect();
+        let read_schema = Arc::new(Schema::new_with_metadata(
+            read_fields,
+            schema.metadata().clone(),
+        ));
+        Self {
+            schema,
+            read_schema,
+            conversions,
+            decimal_excess,
+        }
+    }
+
+    /// Returns the target schema
+    pub fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    /// Returns the schema for reading csv, where converted columns are strings
+    pub fn read_schema(&self) -> SchemaRef {
+        self.read_schema.clone()
+    }
+
+    /// Converts a batch read with [`Converter::read_schema`] to the target schema
+    pub fn convert(&self, batch: RecordBatch) -> Result<RecordBatch> {
+        if self.conversions.iter().all(Option::is_none) {
+            return Ok(batch);
+        }
+        let columns = batch
+            .columns()
+            .iter()
+            .zip(self.schema.fields())
+            .zip(&self.conversions)
+            .enumerate()
+            .map(|(i, ((column, field), conversion))| {
+                let Some(conversion) = conversion else {
+                    return Ok(column.clone());
+                };
+                let values = column.as_any().downcast_ref::<StringArray>().unwrap();
+                let masked;
+                let values = match &conversion.null_values {
+                    Some(null_values) => {
+                        masked = mask_null_values(values, null_values);
+                        &masked
+                    }
+                    None => values,
+                };
+                match &conversion.conversion {
+                    Conversion::Cast => cast_column(values, field),
+                    Conversion::Decimal => {
+                        convert_decimal_column(values, field, self.decimal_excess)
+                    }
+                    Conversion::Format(format) => convert_format_column(values, field, format),
+                    Conversion::Epoch(unit) => convert_epoch_column(values, field, *unit),
+                }
+                .map_err(|err| {
+                    ColumnError {
+                        column: i,
+                        message: err.to_string(),
+                    }
+                    .into()
+                })
+            })
+            .collect::<Result<Vec<ArrayRef>>>()?;
+        Ok(RecordBatch::try_new(self.schema.clone(), columns)?)
+    }
+}
+
+/// Replaces null values with nulls
+fn mask_null_values(values: &StringArray, null_values: &[String]) -> StringArray {
+    values
+        .iter()
+        .map(|value| value.filter(|value| !null_values.iter().any(|null| null == value)))
+        .collect()
+}
+
+/// Casts a string column to the field's data type
+fn cast_column(values: &StringArray, field: &Field) -> Result<ArrayRef> {
+    let options = CastOptions {
+        safe: false,
+        ..Default::default()
+    };
+    cast_with_options(values, field.data_type(), &options)
+        .map_err(|err| anyhow!("{err} (column `{}')", field.name()))
+}
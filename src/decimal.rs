@@ -0,0 +1,291 @@
+use std::{io::Read, sync::Arc};
+
+use anyhow::{Result, anyhow};
+use arrow_array::{
+    Array, ArrayRef, ArrowNativeTypeOp, PrimitiveArray, StringArray,
+    types::{Decimal128Type, Decimal256Type, DecimalType},
+};
+use arrow_cast::parse::parse_decimal;
+use arrow_csv::{ReaderBuilder, reader::Format};
+use arrow_schema::{DECIMAL128_MAX_PRECISION, DataType, Field, Fields, Schema};
+use clap::ValueEnum;
+
+/// Minimum precision of automatically detected decimals. Parquet stores decimals with
+/// precision up to 18 as INT64, so it leaves room for larger values at no cost.
+pub const MIN_DETECTED_PRECISION: u8 = 18;
+
+/// What to do with decimal values that have more fractional digits than the scale
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum DecimalExcess {
+    /// Fail the conversion
+    Error,
+    /// Round half away from zero
+    Round,
+    /// Drop extra digits
+    Truncate,
+}
+
+/// Number of integer and fractional digits of a decimal number
+#[derive(Default, Clone, Copy)]
+struct Digits {
+    integer: usize,
+    fractional: usize,
+}
+
+impl Digits {
+    /// Counts significant integer digits and fractional digits of a number.
+    /// Returns `None` if the string is not a number.
+    fn count(s: &str) -> Option<Self> {
+        let s = s.trim();
+        let s = s.strip_prefix(['-', '+']).unwrap_or(s);
+        let (mantissa, exponent) = match s.split_once(['e', 'E']) {
+            Some((mantissa, exponent)) => (mantissa, exponent.parse::<i64>().ok()?),
+            None => (s, 0),
+        };
+        let (integer, fractional) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+        if integer.is_empty() && fractional.is_empty()
+            || !integer.bytes().all(|b| b.is_ascii_digit())
+            || !fractional.bytes().all(|b| b.is_ascii_digit())
+        {
+            return None;
+        }
+        let integer = integer.trim_start_matches('0').len() as i64;
+        let fractional = fractional.len() as i64;
+        Some(Self {
+            integer: (integer + exponent).max(0) as usize,
+            fractional: (fractional - exponent).max(0) as usize,
+        })
+    }
+
+    /// Widens to fit the other number
+    fn merge(self, other: Self) -> Self {
+        Self {
+            integer: self.integer.max(other.integer),
+            fractional: self.fractional.max(other.fractional),
+        }
+    }
+
+    /// Returns a decimal data type fitting the digits
+    fn data_type(self) -> Result<DataType> {
+        let scale = self.fractional;
+        let precision = (self.integer + scale).max(MIN_DETECTED_PRECISION as usize);
+        let scale = i8::try_from(scale).map_err(|_| anyhow!("Decimal scale is too large"))?;
+        match u8::try_from(precision) {
+            Ok(precision) if precision <= DECIMAL128_MAX_PRECISION => {
+                Ok(DataType::Decimal128(precision, scale))
+            }
+            Ok(precision) if precision <= Decimal256Type::MAX_PRECISION => {
+                Ok(DataType::Decimal256(precision, scale))
+            }
+            _ => Err(anyhow!("Decimal precision {precision} is too large")),
+        }
+    }
+}
+
+/// Detects precision and scale of decimal columns by reading up to `max_records` rows
+/// or the whole file if it's `None`.
+/// `columns` may contain "*" or "__all__" to convert all float columns to decimals.
+pub fn infer_decimals<R: Read>(
+    schema: &mut Schema,
+    columns: &[String],
+    format: &Format,
+    reader: R,
+    max_records: Option<usize>,
+) -> Result<()> {
+    let max_records = max_records.unwrap_or(usize::MAX);
+    let all = columns.iter().any(|c| c == "*" || c == "__all__");
+    for c in columns {
+        if c != "*" && c != "__all__" && schema.field_with_name(c).is_err() {
+            return Err(anyhow!("Column `{c}' is not found in the schema"));
+        }
+    }
+    let projection: Vec<usize> = schema
+        .fields()
+        .iter()
+        .enumerate()
+        .filter(|(_, field)| {
+            columns.contains(field.name()) || all && field.data_type() == &DataType::Float64
+        })
+        .map(|(i, _)| i)
+        .collect();
+    if projection.is_empty() {
+        return Ok(());
+    }
+
+    // Reads the decimal columns as strings to count digits
+    let utf8_fields: Fields = schema
+        .fields()
+        .iter()
+        .map(|field| Field::new(field.name(), DataType::Utf8, true))
+        .collect();
+    let reader = ReaderBuilder::new(Arc::new(Schema::new(utf8_fields)))
+        .with_format(format.clone())
+        .with_projection(projection.clone())
+        .build(reader)?;
+
+    let mut digits = vec![Digits::default(); projection.len()];
+    let mut records = 0;
+    for batch in reader {
+        let batch = batch?;
+        for (i, column) in batch.columns().iter().enumerate() {
+            let values = column.as_any().downcast_ref::<StringArray>().unwrap();
+            for value in values.iter().take(max_records - records).flatten() {
+                let name = schema.field(projection[i]).name();
+                let value_digits = Digits::count(value).ok_or_else(|| {
+                    anyhow!("Column `{name}' contains non-decimal value `{value}'")
+                })?;
+                digits[i] = digits[i].merge(value_digits);
+            }
+        }
+        records += batch.num_rows();
+        if records >= max_records {
+            break;
+        }
+    }
+
+    let mut new_fields: Vec<Field> = schema.fields().iter().map(|f| f.as_ref().clone()).collect();
+    for (i, digits) in projection.into_iter().zip(digits) {
+        new_fields[i] = new_fields[i].clone().with_data_type(digits.data_type()?);
+    }
+    schema.fields = Fields::from(new_fields);
+    Ok(())
+}
+
+/// Converts a string column to decimals of the field's data type
+pub fn convert_decimal_column(
+    values: &StringArray,
+    field: &Field,
+    excess: DecimalExcess,
+) -> Result<ArrayRef> {
+    match *field.data_type() {
+        DataType::Decimal128(precision, scale) => {
+            convert_column::<Decimal128Type>(values, field.name(), precision, scale, excess)
+        }
+        DataType::Decimal256(precision, scale) => {
+            convert_column::<Decimal256Type>(values, field.name(), precision, scale, excess)
+        }
+        ref data_type => Err(anyhow!(
+            "Column `{}' of type {data_type} is not a decimal",
+            field.name()
+        )),
+    }
+}
+
+/// Converts a string column to decimals
+fn convert_column<T: DecimalType>(
+    values: &StringArray,
+    name: &str,
+    precision: u8,
+    scale: i8,
+    excess: DecimalExcess,
+) -> Result<ArrayRef> {
+    let array = values
+        .iter()
+        .map(|value| {
+            value
+                .map(|value| convert_value::<T>(value.trim(), precision, scale, excess))
+                .transpose()
+                .map_err(|err| anyhow!("{err} (column `{name}')"))
+        })
+        .collect::<Result<PrimitiveArray<T>>>()?
+        .with_precision_and_scale(precision, scale)?;
+    Ok(Arc::new(array))
+}
+
+/// Converts a string to a decimal applying the excess policy
+fn convert_value<T: DecimalType>(
+    value: &str,
+    precision: u8,
+    scale: i8,
+    excess: DecimalExcess,
+) -> Result<T::Native> {
+    // Fractional digits beyond the scale. E-notation is left to arrow.
+    let (parsed, dropped) = match value.split_once('.') {
+        Some((integer, fractional)) if !fractional.contains(['e', 'E']) && scale >= 0 => {
+            let dropped = fractional.get(scale as usize..).unwrap_or_default();
+            // Arrow doesn't stop at the decimal point when the scale is 0
+            let parsed = match integer.trim_start_matches(['-', '+']) {
+                _ if scale > 0 => value,
+                "" => "0",
+                _ => integer,
+            };
+            (parsed, dropped)
+        }
+        _ => (value, ""),
+    };
+    let result = parse_decimal::<T>(parsed, precision, scale)?;
+    if dropped.bytes().all(|b| b == b'0') {
+        return Ok(result);
+    }
+    match excess {
+        DecimalExcess::Error => Err(anyhow!(
+            "Value {value} has more than {scale} fractional digits"
+        )),
+        DecimalExcess::Truncate => Ok(result),
+        DecimalExcess::Round => {
+            if dropped.as_bytes()[0] < b'5' {
+                return Ok(result);
+            }
+            let result = if value.starts_with('-') {
+                result.sub_checked(T::Native::ONE)?
+            } else {
+                result.add_checked(T::Native::ONE)?
+            };
+            T::validate_decimal_precision(result, precision)?;
+            Ok(result)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn convert(value: &str, scale: i8, excess: DecimalExcess) -> Result<i128> {
+        convert_value::<Decimal128Type>(value, 18, scale, excess)
+    }
+
+    #[test]
+    fn count_digits() {
+        let count = |s| Digits::count(s).map(|d| (d.integer, d.fractional));
+        assert_eq!(count("12.5"), Some((2, 1)));
+        assert_eq!(count("-0012.500"), Some((2, 3)));
+        assert_eq!(count("+.25"), Some((0, 2)));
+        assert_eq!(count("7."), Some((1, 0)));
+        assert_eq!(count("1.25e2"), Some((3, 0)));
+        assert_eq!(count("125E-4"), Some((0, 4)));
+        assert_eq!(count(" 42 "), Some((2, 0)));
+        assert_eq!(count(""), None);
+        assert_eq!(count("."), None);
+        assert_eq!(count("1.2.3"), None);
+        assert_eq!(count("1e"), None);
+        assert_eq!(count("abc"), None);
+    }
+
+    #[test]
+    fn convert_with_scale() {
+        assert_eq!(convert("12.34", 2, DecimalExcess::Error).unwrap(), 1234);
+        assert_eq!(convert("-12.3", 2, DecimalExcess::Error).unwrap(), -1230);
+        assert_eq!(convert("12.3400", 2, DecimalExcess::Error).unwrap(), 1234);
+        assert_eq!(convert("1.5e1", 2, DecimalExcess::Error).unwrap(), 1500);
+        assert!(convert("12.345", 2, DecimalExcess::Error).is_err());
+        assert_eq!(convert("12.345", 2, DecimalExcess::Truncate).unwrap(), 1234);
+        assert_eq!(convert("12.345", 2, DecimalExcess::Round).unwrap(), 1235);
+        assert_eq!(convert("12.344", 2, DecimalExcess::Round).unwrap(), 1234);
+        assert_eq!(convert("-12.345", 2, DecimalExcess::Round).unwrap(), -1235);
+    }
+
+    #[test]
+    fn convert_with_zero_scale() {
+        assert_eq!(convert("12", 0, DecimalExcess::Error).unwrap(), 12);
+        assert_eq!(convert("12.0", 0, DecimalExcess::Error).unwrap(), 12);
+        assert!(convert("12.5", 0, DecimalExcess::Error).is_err());
+        assert_eq!(convert("12.5", 0, DecimalExcess::Truncate).unwrap(), 12);
+        assert_eq!(convert("12.5", 0, DecimalExcess::Round).unwrap(), 13);
+        assert_eq!(convert("12.75", 0, DecimalExcess::Round).unwrap(), 13);
+        assert_eq!(convert("12.25", 0, DecimalExcess::Round).unwrap(), 12);
+        assert_eq!(convert("-12.5", 0, DecimalExcess::Round).unwrap(), -13);
+        assert_eq!(convert("-.5", 0, DecimalExcess::Round).unwrap(), -1);
+        assert_eq!(convert(".4", 0, DecimalExcess::Truncate).unwrap(), 0);
+    }
+}
@@ -0,0 +1,373 @@
+use std::io::Read;
+
+use clap::ValueEnum;
+use csv_core::{ReadRecordResult, Reader};
+use encoding_rs::{DecoderResult, Encoding, UTF_8};
+
+use crate::rewindable_reader::Rewind;
+
+/// Size of data read at once by the decoding readers
+const BUFFER_SIZE: usize = 1 << 16;
+
+/// Parses an encoding name like `windows-1251`, `latin1` or `utf-16le`
+pub fn parse_encoding(name: &str) -> Result<&'static Encoding, String> {
+    Encoding::for_label(name.as_bytes()).ok_or_else(|| format!("Unknown encoding `{name}'"))
+}
+
+/// What to do with invalid UTF-8 in csv files
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum InvalidUtf8 {
+    /// Replace invalid sequences with the replacement character
+    Replace,
+    /// Stop with an error
+    Error,
+    /// Skip rows with invalid sequences
+    SkipRow,
+}
+
+/// Settings of converting csv data to UTF-8
+#[derive(Clone)]
+pub struct TextSettings {
+    /// Name of the file in warnings
+    pub filename: String,
+    /// Encoding of data without a byte order mark
+    pub encoding: &'static Encoding,
+    pub invalid_utf8: InvalidUtf8,
+    /// Reader of the csv dialect finding rows to skip and whether the first row is the header,
+    /// which is never skipped
+    pub records: Option<(Reader, bool)>,
+}
+
+/// Reader converting data to UTF-8. A UTF-8 or UTF-16 byte order mark overrides the encoding
+/// and is stripped. UTF-8 data is passed as is unless invalid sequences are replaced.
+/// Otherwise invalid sequences are passed as invalid UTF-8 bytes.
+pub struct Utf8Reader<R> {
+    inner: R,
+    settings: TextSettings,
+    /// Decoder of the data or `None` if it's passed as is
+    decoder: Option<encoding_rs::Decoder>,
+    /// Length of the stripped byte order mark
+    bom_length: usize,
+    /// Whether data is read after choosing the encoding
+    started: bool,
+    /// Data read from `inner` and not decoded yet
+    input: Vec<u8>,
+    input_start: usize,
+    /// Decoded data not returned yet
+    output: Vec<u8>,
+    output_start: usize,
+    eof: bool,
+    /// Whether the decoder is flushed at the end of data
+    finished: bool,
+}
+
+impl<R: Read> Utf8Reader<R> {
+    /// Creates a reader choosing the encoding by the first bytes of data
+    pub fn new(mut inner: R, settings: TextSettings) -> std::io::Result<Self> {
+        let mut input = Vec::with_capacity(BUFFER_SIZE);
+        inner.by_ref().take(3).read_to_end(&mut input)?;
+        let (encoding, bom_length) = Encoding::for_bom(&input).unwrap_or((settings.encoding, 0));
+        let decoder = (encoding != UTF_8 || settings.invalid_utf8 == InvalidUtf8::Replace)
+            .then(|| encoding.new_decoder_without_bom_handling());
+        Ok(Self {
+            inner,
+            settings,
+            decoder,
+            bom_length,
+            started: false,
+            input,
+            input_start: bom_length,
+            output: Vec::with_capacity(BUFFER_SIZE),
+            output_start: 0,
+            eof: false,
+            finished: false,
+        })
+    }
+
+    /// Decodes the next part of data into `output`, which is left empty at the end of data
+    fn decode(&mut self) -> std::io::Result<()> {
+        let decoder = self.decoder.as_mut().unwrap();
+        self.output.clear();
+        self.output_start = 0;
+        while self.output.is_empty() && !self.finished {
+            if self.input_start == self.input.len() && !self.eof {
+                self.input.resize(BUFFER_SIZE, 0);
+                let read = self.inner.read(&mut self.input)?;
+                self.input.truncate(read);
+                self.input_start = 0;
+                self.eof = read == 0;
+            }
+            let mut written = 0;
+            loop {
+                let input = &self.input[self.input_start..];
+                let length = decoder
+                    .max_utf8_buffer_length_without_replacement(input.len())
+                    .unwrap();
+                self.output.resize(written + length, 0);
+                let (result, read, decoded) = decoder.decode_to_utf8_without_replacement(
+                    input,
+                    &mut self.output[written..],
+                    self.eof,
+                );
+                self.input_start += read;
+                written += decoded;
+                if let DecoderResult::Malformed(_, _) = result {
+                    let marker: &[u8] = match self.settings.invalid_utf8 {
+                        InvalidUtf8::Replace => "\u{FFFD}".as_bytes(),
+                        // Left for the csv parser or `ValidRows`
+                        _ => b"\xFF",
+                    };
+                    self.output.truncate(written);
+                    self.output.extend_from_slice(marker);
+                    written += marker.len();
+                    continue;
+                }
+                self.finished = self.eof && self.input_start == self.input.len();
+                break;
+            }
+            self.output.truncate(written);
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for Utf8Reader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.started = true;
+        if self.decoder.is_none() {
+            // Bytes read to find the byte order mark are returned first
+            if self.input_start < self.input.len() {
+                let read = (&self.input[self.input_start..]).read(buf)?;
+                self.input_start += read;
+                return Ok(read);
+            }
+            return self.inner.read(buf);
+        }
+        if self.output_start == self.output.len() {
+            self.decode()?;
+        }
+        let read = (&self.output[self.output_start..]).read(buf)?;
+        self.output_start += read;
+        Ok(read)
+    }
+}
+
+impl<R: Rewind> Rewind for Utf8Reader<R> {
+    fn rewind(self) -> std::io::Result<Self> {
+        Utf8Reader::new(self.inner.rewind()?, self.settings)
+    }
+
+    fn mapped(&self) -> Option<&[u8]> {
+        if self.started || self.decoder.is_some() {
+            return None;
+        }
+        Some(&self.inner.mapped()?[self.bom_length..])
+    }
+
+    fn sample(&mut self, terminator: u8) -> Option<std::io::Result<Vec<u8>>> {
+        if self.started || self.decoder.is_some() {
+            return None;
+        }
+        let bom_length = self.bom_length;
+        let sample = self.inner.sample(terminator)?;
+        Some(sample.map(|sample| sample[bom_length.min(sample.len())..].to_vec()))
+    }
+}
+
+/// Reader dropping csv records with invalid UTF-8. Records are passed as is without
+/// the reader of the csv dialect.
+pub struct ValidRows<R> {
+    inner: R,
+    settings: TextSettings,
+    /// Data read from `inner`
+    data: Vec<u8>,
+    /// Position of the next returned byte in `data`
+    position: usize,
+    /// End of checked records in `data`
+    checked: usize,
+    /// End of data scanned for the end of the next record
+    scanned: usize,
+    /// Number of checked records
+    records: u64,
+    skipped: u64,
+    /// Whether skipped records are reported. They are reported once for all passes over data.
+    reported: bool,
+    eof: bool,
+    ended: bool,
+}
+
+impl<R: Read> ValidRows<R> {
+    /// Creates a reader dropping records if the settings have the reader of the csv dialect
+    pub fn new(inner: R, settings: TextSettings) -> Self {
+        Self::with_reported(inner, settings, false)
+    }
+
+    fn with_reported(inner: R, settings: TextSettings, reported: bool) -> Self {
+        Self {
+            inner,
+            settings,
+            data: vec![],
+            position: 0,
+            checked: 0,
+            scanned: 0,
+            records: 0,
+            skipped: 0,
+            reported,
+            eof: false,
+            ended: false,
+        }
+    }
+
+    /// Reads more data and checks complete records in it
+    fn check_records(&mut self) -> std::io::Result<()> {
+        let (records, header) = self.settings.records.as_mut().unwrap();
+        self.data.drain(..self.position);
+        self.checked -= self.position;
+        self.scanned -= self.position;
+        self.position = 0;
+        if !self.eof {
+            let length = self.data.len();
+            self.data.resize(length + BUFFER_SIZE, 0);
+            let read = self.inner.read(&mut self.data[length..])?;
+            self.data.truncate(length + read);
+            self.eof = read == 0;
+        }
+        let mut output = [0; 1 << 12];
+        let mut ends = [0; 1 << 8];
+        // Empty input means the end of data for the csv reader
+        while self.scanned < self.data.len() || self.eof {
+            let (result, bytes, _, _) =
+                records.read_record(&self.data[self.scanned..], &mut output, &mut ends);
+            self.scanned += bytes;
+            match result {
+                ReadRecordResult::Record => {
+                    let record = &self.data[self.checked..self.scanned];
+                    if self.records == 0 && *header || std::str::from_utf8(record).is_ok() {
+                        self.checked = self.scanned;
+                    } else {
+                        self.data.drain(self.checked..self.scanned);
+                        self.scanned = self.checked;
+                        self.skipped += 1;
+                    }
+                    self.records += 1;
+                }
+                ReadRecordResult::End => {
+                    self.checked = self.data.len();
+                    self.ended = true;
+                    if self.skipped > 0 && !self.reported {
+                        eprintln!(
+                            "Warning: {}: {} rows with invalid UTF-8 are skipped",
+                            self.settings.filename, self.skipped
+                        );
+                        self.reported = true;
+                    }
+                    break;
+                }
+                ReadRecordResult::InputEmpty => break,
+                ReadRecordResult::OutputFull | ReadRecordResult::OutputEndsFull => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for ValidRows<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.settings.records.is_none() {
+            return self.inner.read(buf);
+        }
+        while self.position == self.checked && !self.ended {
+            self.check_records()?;
+        }
+        let read = (&self.data[self.position..self.checked]).read(buf)?;
+        self.position += read;
+        Ok(read)
+    }
+}
+
+impl<R: Rewind> Rewind for ValidRows<R> {
+    fn rewind(mut self) -> std::io::Result<Self> {
+        // The previous pass may have stopped inside a quoted field
+        if let Some((records, _)) = &mut self.settings.records {
+            records.reset();
+        }
+        let reported = self.reported;
+        Ok(ValidRows::with_reported(
+            self.inner.rewind()?,
+            self.settings,
+            reported,
+        ))
+    }
+
+    fn mapped(&self) -> Option<&[u8]> {
+        self.settings
+            .records
+            .is_none()
+            .then(|| self.inner.mapped())?
+    }
+
+    fn sample(&mut self, terminator: u8) -> Option<std::io::Result<Vec<u8>>> {
+        match self.settings.records {
+            Some(_) => None,
+            None => self.inner.sample(terminator),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    impl Rewind for Cursor<Vec<u8>> {
+        fn rewind(mut self) -> std::io::Result<Self> {
+            self.set_position(0);
+            Ok(self)
+        }
+
+        fn mapped(&self) -> Option<&[u8]> {
+            None
+        }
+
+        fn sample(&mut self, _terminator: u8) -> Option<std::io::Result<Vec<u8>>> {
+            None
+        }
+    }
+
+    fn settings() -> TextSettings {
+        TextSettings {
+            filename: "test.csv".to_string(),
+            encoding: UTF_8,
+            invalid_utf8: InvalidUtf8::SkipRow,
+            records: Some((Reader::new(), true)),
+        }
+    }
+
+    #[test]
+    fn rewind_after_reading_inside_quoted_field() {
+        let mut data = b"id,note\n".to_vec();
+        let mut valid = data.clone();
+        data.extend_from_slice(b"0,\"\xFF\n\"\n");
+        for id in 1..200 {
+            let row = format!("{id},\"{}\n{}\"\n", "x".repeat(1000), "y".repeat(10));
+            data.extend_from_slice(row.as_bytes());
+            valid.extend_from_slice(row.as_bytes());
+        }
+        let quotes = data[..BUFFER_SIZE]
+            .iter()
+            .filter(|&&byte| byte == b'"')
+            .count();
+        assert_eq!(
+            quotes % 2,
+            1,
+            "the first read must end inside a quoted field"
+        );
+
+        let mut rows = ValidRows::new(Cursor::new(data), settings());
+        rows.read_exact(&mut [0; 1]).unwrap();
+        let mut replayed = vec![];
+        rows.rewind().unwrap().read_to_end(&mut replayed).unwrap();
+        assert_eq!(replayed, valid);
+    }
+}
@@ -0,0 +1,93 @@
+use anyhow::{Result, anyhow};
+use arrow_schema::Schema;
+use regex::Regex;
+
+use crate::properties::split_column_spec;
+
+/// Parses a single byte character. Accepts a literal ASCII character, an escape sequence
+/// (`\t`, `\n`, `\r`, `\0`) or a name like `tab`, `comma`, `semicolon`, `pipe` or `space`.
+pub fn parse_char(s: &str) -> Result<u8> {
+    let c = match s.to_ascii_lowercase().as_str() {
+        "\\t" | "tab" => b'\t',
+        "\\n" | "lf" => b'\n',
+        "\\r" | "cr" => b'\r',
+        "\\0" | "nul" => b'\0',
+        "comma" => b',',
+        "semicolon" => b';',
+        "colon" => b':',
+        "pipe" => b'|',
+        "space" => b' ',
+        "quote" => b'"',
+        "apostrophe" => b'\'',
+        "backslash" => b'\\',
+        "hash" => b'#',
+        _ => match s.as_bytes() {
+            [c] if c.is_ascii() => *c,
+            _ => return Err(anyhow!("Expected a single ASCII character, got `{s}'")),
+        },
+    };
+    Ok(c)
+}
+
+/// Parses a `COLUMN=VALUE[,VALUE...]` specification, e.g. `price=NA,-`
+pub fn parse_column_null_values(spec: &str) -> Result<(String, Vec<String>)> {
+    let (column, values) = split_column_spec(spec, "VALUES")?;
+    Ok((column, values.split(',').map(str::to_string).collect()))
+}
+
+/// Builds a regex matching an empty string and any of the values
+fn null_regex<'a>(values: impl Iterator<Item = &'a String>) -> Result<Regex> {
+    let alternatives: Vec<String> = values.map(|value| regex::escape(value)).collect();
+    Ok(Regex::new(&format!("^(?:|{})$", alternatives.join("|")))?)
+}
+
+/// Values treated as nulls. Empty strings are always nulls.
+pub struct NullValues {
+    /// Regex matching null values of all columns
+    pub regex: Option<Regex>,
+    /// Regex matching null values of all columns and column specific ones.
+    /// It is used for schema inference, since arrow-csv doesn't support per-column nulls.
+    pub inference_regex: Option<Regex>,
+    /// Column specific null values
+    columns: Vec<(String, Vec<String>)>,
+}
+
+impl NullValues {
+    /// Creates null values from global and column specific values
+    pub fn new(values: &[String], columns: Vec<(String, Vec<String>)>) -> Result<Self> {
+        let regex = if values.is_empty() {
+            None
+        } else {
+            Some(null_regex(values.iter())?)
+        };
+        let inference_regex = if columns.is_empty() {
+            regex.clone()
+        } else {
+            let column_values = columns.iter().flat_map(|(_, values)| values);
+            Some(null_regex(values.iter().chain(column_values))?)
+        };
+        Ok(Self {
+            regex,
+            inference_regex,
+            columns,
+        })
+    }
+
+    /// Returns column specific null values
+    pub fn column(&self, column: &str) -> Option<&[String]> {
+        self.columns
+            .iter()
+            .find(|(c, _)| c == column)
+            .map(|(_, values)| values.as_slice())
+    }
+
+    /// Returns an error if a column is not found in the schema
+    pub fn check_columns(&self, schema: &Schema) -> Result<()> {
+        for (column, _) in &self.columns {
+            if schema.field_with_name(column).is_err() {
+                return Err(anyhow!("Column `{column}' is not found in the schema"));
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,30 +1,74 @@
 use std::{
     collections::HashMap,
-    fs::remove_file,
-    io::IsTerminal,
-    path::{Path, PathBuf},
-    sync::Arc,
+    fs::{create_dir_all, remove_file},
+    io::{IsTerminal, Write},
+    num::NonZeroUsize,
+    path::{Component, Path, PathBuf},
+    process::ExitCode,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+    thread::{self, available_parallelism},
 };
 
 use anyhow::{Result, anyhow};
+use arrow_array::{RecordBatch, StringArray};
 use arrow_csv::{ReaderBuilder, reader::Format};
-use arrow_schema::{DataType, Field, Fields, Schema};
+use arrow_schema::{ArrowError, DataType, Field, Fields, Schema, SchemaRef, TimeUnit};
 use clap::{Parser, ValueHint};
 use parquet::{
-    arrow::ArrowWriter,
-    basic::{Compression, GzipLevel},
+    basic::{Compression, Encoding},
     file::properties::WriterProperties,
 };
 
+mod archive;
+mod compression;
+mod convert;
+mod decimal;
+mod encoding;
+mod format;
+mod output;
+mod pipeline;
+mod properties;
 mod rewindable_reader;
+mod sample;
+mod schema;
+mod stdio;
+mod summary;
 mod tempfile;
+mod timestamp;
+mod writer;
 
-use rewindable_reader::RewindableReader;
+use archive::{is_zip, member_size, members, split_member};
+use compression::parse_compression;
+use convert::Converter;
+use decimal::{DecimalExcess, infer_decimals};
+use encoding::{InvalidUtf8, TextSettings, parse_encoding};
+use format::{NullValues, parse_char, parse_column_null_values};
+use output::{IfExists, check_output, create_output, publish};
+use pipeline::read_batches;
+use properties::{WriterSettings, parse_column_compression, parse_column_encoding};
+use rewindable_reader::{InputCompression, RewindableReader};
+use schema::{
+    ColumnError, check_header, describe_parse_error, load_schema, merge_schemas, parse_column_type,
+    parse_time_unit, widen,
+};
+use stdio::{STDIO, is_stdio, stdin_size};
+use summary::{FileReport, Status, Summary, file_size};
 use tempfile::TempFile;
+use timestamp::{TimestampSettings, parse_column_epoch, parse_column_format, parse_timezone};
+use writer::ParquetWriter;
 
-/// Number of rows to read from csv to infer schema
+/// Default number of rows to read from csv to infer schema
 pub const MAX_READ_RECORDS: usize = 8192;
 
+/// Supported input file extensions and their default delimiters
+pub const INPUT_EXTENSIONS: [(&str, u8); 3] = [(".csv", b','), (".tsv", b'\t'), (".psv", b'|')];
+
+/// Supported extensions of compressed input files
+pub const COMPRESSION_EXTENSIONS: [&str; 6] = [".gz", ".zst", ".bz2", ".xz", ".lz4", ".sz"];
+
 /// Default integer data type
 pub const DEFAULT_INT_TYPE: DataType = DataType::Int64;
 
@@ -35,10 +79,24 @@ pub const DEFAULT_FLOAT_TYPE: DataType = DataType::Float32;
 #[clap(version = env!("CARGO_PKG_VERSION"))]
 #[command(about = "CSV to Apache Parquet converter")]
 struct Args {
-    /// Input .csv[.gz] files
+    /// Input .csv, .tsv or .psv files, optionally compressed with .gz, .zst, .bz2, .xz, .lz4
+    /// or .sz, and .zip archives of them
     #[clap(name = "CSV-FILES", required=true, value_parser, value_hint = ValueHint::AnyPath)]
     input: Vec<PathBuf>,
 
+    /// Compression of input files. Detected by the first bytes of every file by default.
+    #[clap(long, value_name = "CODEC")]
+    input_compression: Option<InputCompression>,
+
+    /// Encoding of input files, e.g. `windows-1251`, `latin1` or `utf-16le`. Defaults to UTF-8.
+    /// A UTF-8 or UTF-16 byte order mark overrides it and is stripped.
+    #[clap(long, value_name = "NAME", value_parser = parse_encoding)]
+    encoding: Option<&'static encoding_rs::Encoding>,
+
+    /// What to do with invalid UTF-8 in input files or characters invalid in the encoding
+    #[clap(long, default_value = "error", value_name = "POLICY")]
+    invalid_utf8: InvalidUtf8,
+
     /// Comma separated list of Int32 columns. Use "*" or "__all__" to set Int32 as the default
     /// type for integer columns.
     #[clap(long, value_delimiter = ',', value_name = "COLUMNS")]
@@ -57,6 +115,179 @@ struct Args {
     #[clap(long, value_delimiter = ',', value_name = "COLUMNS")]
     f64: Option<Vec<String>>,
 
+    /// Treat the first row as data. Columns are named `column_1`, `column_2`, etc.
+    #[clap(long)]
+    no_header: bool,
+
+    /// Comma separated list of column names. Replaces the names from the header or
+    /// the generated ones when used with --no-header
+    #[clap(long, value_delimiter = ',', value_name = "NAMES")]
+    names: Option<Vec<String>>,
+
+    /// Field delimiter, e.g. `;`, `\t` or `tab`. Defaults to `,` for .csv, `\t` for .tsv
+    /// and `|` for .psv files
+    #[clap(short, long, value_name = "CHAR", value_parser = parse_char)]
+    delimiter: Option<u8>,
+
+    /// Quote character
+    #[clap(long, value_name = "CHAR", value_parser = parse_char)]
+    quote: Option<u8>,
+
+    /// Escape character
+    #[clap(long, value_name = "CHAR", value_parser = parse_char)]
+    escape: Option<u8>,
+
+    /// Comment character. Lines starting with it are ignored
+    #[clap(long, value_name = "CHAR", value_parser = parse_char)]
+    comment: Option<u8>,
+
+    /// Record terminator, e.g. `\n` or `\r`. Defaults to CRLF or LF
+    #[clap(long, value_name = "CHAR", value_parser = parse_char)]
+    terminator: Option<u8>,
+
+    /// Comma separated list of values treated as nulls in all columns, e.g. `NA,NULL,\N`.
+    /// Empty strings are always nulls.
+    #[clap(long, value_delimiter = ',', value_name = "VALUES")]
+    null_values: Vec<String>,
+
+    /// Comma separated list of values treated as nulls in a column, e.g. `price=NA,-`.
+    /// Can be used multiple times.
+    #[clap(long, value_name = "COLUMN=VALUES", value_parser = parse_column_null_values)]
+    column_null_values: Vec<(String, Vec<String>)>,
+
+    /// Parquet compression codec with an optional level: snappy, gzip[:0-9], zstd[:1-22],
+    /// lz4_raw, brotli[:0-11] or uncompressed
+    #[clap(long, default_value = "gzip:8", value_name = "CODEC[:LEVEL]", value_parser = parse_compression)]
+    compression: Compression,
+
+    /// Parquet compression codec for a column, e.g. `blob=zstd:15`. Can be used multiple times.
+    #[clap(long, value_name = "COLUMN=CODEC[:LEVEL]", value_parser = parse_column_compression)]
+    column_compression: Vec<(String, Compression)>,
+
+    /// Parquet encoding for a column, e.g. `id=DELTA_BINARY_PACKED`. Disables dictionary
+    /// encoding for the column. Can be used multiple times.
+    #[clap(long, value_name = "COLUMN=ENCODING", value_parser = parse_column_encoding)]
+    column_encoding: Vec<(String, Encoding)>,
+
+    /// Comma separated list of columns with dictionary encoding disabled
+    #[clap(long, value_delimiter = ',', value_name = "COLUMNS")]
+    no_dictionary: Vec<String>,
+
+    /// Timestamp format of a column in `strftime` syntax, e.g. `ts=%d/%m/%Y %H:%M`. Columns with
+    /// the date32 type are parsed as dates. Can be used multiple times.
+    #[clap(long, value_name = "COLUMN=FORMAT", value_parser = parse_column_format)]
+    timestamp_format: Vec<(String, String)>,
+
+    /// Column holding time since the Unix epoch in s, ms, us or ns, e.g. `created=ms`.
+    /// Can be used multiple times.
+    #[clap(long, value_name = "COLUMN=UNIT", value_parser = parse_column_epoch)]
+    epoch: Vec<(String, TimeUnit)>,
+
+    /// Time zone of timestamps without an offset, e.g. `Europe/Berlin` or `+02:00`.
+    /// Timestamps are stored in UTC and annotated with the time zone.
+    #[clap(long, value_name = "TZ", value_parser = parse_timezone)]
+    timezone: Option<String>,
+
+    /// Time unit of stored timestamps: s, ms, us or ns
+    #[clap(long, value_name = "UNIT", value_parser = parse_time_unit)]
+    timestamp_unit: Option<TimeUnit>,
+
+    /// Number of rows to read to infer the schema
+    #[clap(long, value_name = "N", default_value_t = MAX_READ_RECORDS)]
+    infer_rows: usize,
+
+    /// Read the whole file to infer the schema
+    #[clap(long, conflicts_with_all = ["infer_rows", "infer_sample"])]
+    infer_all: bool,
+
+    /// Infer the schema from blocks read across the whole file rather than from its head.
+    /// Compressed files are sampled from the head only.
+    #[clap(long, conflicts_with = "infer_rows")]
+    infer_sample: bool,
+
+    /// Use the schema from a JSON file (as printed by --print-schema) instead of inferring it
+    #[clap(long, value_name = "FILE", value_hint = ValueHint::FilePath,
+        conflicts_with_all = ["names", "i32", "i64", "f32", "f64", "types", "decimal",
+            "timezone", "timestamp_unit"])]
+    schema: Option<PathBuf>,
+
+    /// Comma separated list of Decimal columns. Precision and scale are detected automatically.
+    /// Use "*" or "__all__" to store all float columns as decimals.
+    #[clap(long, value_delimiter = ',', value_name = "COLUMNS")]
+    decimal: Option<Vec<String>>,
+
+    /// What to do with decimal values which have more fractional digits than the column scale
+    #[clap(long, value_enum, default_value_t = DecimalExcess::Error)]
+    decimal_excess: DecimalExcess,
+
+    /// Data type for a column, e.g. `zip=utf8`, `amount=decimal(18,4)` or `ts=timestamp[ms,UTC]`.
+    /// Supported types: utf8, large_utf8, binary, boolean, int8-int64, uint8-uint64,
+    /// float16-float64, date32, date64, timestamp[UNIT[,TZ]], decimal(P,S), decimal256(P,S).
+    /// Can be used multiple times.
+    #[clap(long = "type", value_name = "COLUMN=TYPE", value_parser = parse_column_type)]
+    types: Vec<(String, DataType)>,
+
+    /// Widen the data type of a column and restart the conversion when a value doesn't fit it:
+    /// integers are widened to Float64 and other types to Utf8
+    #[clap(long, conflicts_with = "schema")]
+    adaptive: bool,
+
+    /// Infer schemas of all files first and convert them with the same merged schema.
+    /// Conflicting data types are widened.
+    #[clap(long, conflicts_with_all = ["schema", "adaptive"])]
+    unify_schema: bool,
+
+    /// Write the csv file into this Parquet file. If several csv files are given, they are
+    /// all written into it: files must have the same columns, their schemas are merged
+    /// unless --schema is set.
+    #[clap(short, long, value_hint = ValueHint::FilePath)]
+    output: Option<PathBuf>,
+
+    /// Write Parquet files into this directory instead of the directories of csv files
+    #[clap(long, value_hint = ValueHint::DirPath, conflicts_with = "output")]
+    output_dir: Option<PathBuf>,
+
+    /// Mirror the directory structure of csv files relative to this directory in --output-dir
+    #[clap(long, value_hint = ValueHint::DirPath, requires = "output_dir")]
+    base_dir: Option<PathBuf>,
+
+    /// Add a column holding the name of the csv file each row came from.
+    /// The column is named "source_file" unless NAME is set.
+    #[clap(
+        long,
+        value_name = "NAME",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "source_file",
+        requires = "output"
+    )]
+    source_column: Option<String>,
+
+    /// What to do if a Parquet file already exists
+    #[clap(long, value_enum, default_value_t = IfExists::Skip)]
+    if_exists: IfExists,
+
+    /// Remove temporary files left by interrupted conversions instead of skipping
+    /// their Parquet files
+    #[clap(long)]
+    clean_stale_tmp: bool,
+
+    /// Number of files converted in parallel. 0 means the number of CPUs.
+    #[clap(short, long, default_value_t = 1)]
+    jobs: usize,
+
+    /// Number of threads parsing and encoding a file. 0 means the number of CPUs.
+    #[clap(short, long, default_value_t = 1)]
+    threads: usize,
+
+    /// Continue with other files after a file fails
+    #[clap(short, long)]
+    keep_going: bool,
+
+    /// Write a JSON report about processed files
+    #[clap(long, value_hint = ValueHint::FilePath)]
+    report: Option<PathBuf>,
+
     /// Print the inferred Parquet schema and exit
     #[clap(short, long)]
     print_schema: bool,
@@ -66,13 +297,42 @@ struct Args {
     rm: bool,
 }
 
-/// Consolidate i32, i64, f32 and f32 parameters to a HashMap
+/// Settings shared by all processed files
+struct Settings {
+    /// Data types of columns
+    overrides: HashMap<String, DataType>,
+    /// Data type for integer columns
+    default_int_type: DataType,
+    /// Data type for float columns
+    default_float_type: DataType,
+    /// Schema used instead of the inferred one
+    explicit_schema: Option<Schema>,
+    /// Timestamp parsing settings
+    timestamps: TimestampSettings,
+    /// Values treated as nulls
+    null_values: NullValues,
+    /// Parquet writer settings
+    writer: WriterSettings,
+    /// Standard input read by all passes over it. It's taken while it's read.
+    stdin: Mutex<Option<RewindableReader>>,
+    /// Size of the standard input, see [`stdin_size`]
+    stdin_size: u64,
+    /// Compression of input files used instead of the detected one
+    input_compression: Option<InputCompression>,
+}
+
+/// Consolidate i32, i64, f32, f64 and type parameters to a HashMap
 fn consolidate_types(args: &mut Args) -> Result<(HashMap<String, DataType>, DataType, DataType)> {
     let mut overrides = HashMap::new();
     let mut default_int_type = DEFAULT_INT_TYPE;
     let mut default_float_type = DEFAULT_FLOAT_TYPE;
 
-    if args.i32.is_none() && args.i64.is_none() && args.f32.is_none() && args.f64.is_none() {
+    if args.i32.is_none()
+        && args.i64.is_none()
+        && args.f32.is_none()
+        && args.f64.is_none()
+        && args.types.is_empty()
+    {
         // There is no need to make any changes to the current scheme
         return Ok((overrides, default_int_type, default_float_type));
     }
@@ -133,9 +393,42 @@ fn consolidate_types(args: &mut Args) -> Result<(HashMap<String, DataType>, Data
             ));
         }
     }
+    for (c, datatype) in std::mem::take(&mut args.types) {
+        if overrides.insert(c.clone(), datatype).is_some() {
+            return Err(anyhow!(
+                "Data type for column `{c}' was specified multiple times",
+            ));
+        }
+    }
+    for c in args.decimal.iter().flatten() {
+        if overrides.contains_key(c) {
+            return Err(anyhow!(
+                "Data type for column `{c}' was specified multiple times",
+            ));
+        }
+    }
     Ok((overrides, default_int_type, default_float_type))
 }
 
+/// Replaces column names in the schema
+fn rename_columns(schema: &mut Schema, names: &[String]) -> Result<()> {
+    if names.len() != schema.fields().len() {
+        return Err(anyhow!(
+            "{} column names were specified, but {} columns were found",
+            names.len(),
+            schema.fields().len()
+        ));
+    }
+    let new_fields: Vec<Field> = schema
+        .fields()
+        .iter()
+        .zip(names)
+        .map(|(field, name)| field.as_ref().clone().with_name(name))
+        .collect();
+    schema.fields = Fields::from(new_fields);
+    Ok(())
+}
+
 /// Applies user-provided data types to the schema
 fn apply_schema_overrides(
     schema: &mut Schema,
@@ -170,116 +463,886 @@ fn apply_schema_overrides(
     Ok(())
 }
 
-/// Converts a single csv file to parquet
-fn process(
+/// Splits a filename into a stem and the default delimiter for its extension.
+/// Returns `None` if the extension is not supported.
+fn split_extension(basename: &str) -> Option<(&str, u8)> {
+    let basename = COMPRESSION_EXTENSIONS
+        .iter()
+        .find_map(|ext| basename.strip_suffix(ext))
+        .unwrap_or(basename);
+    INPUT_EXTENSIONS
+        .iter()
+        .find_map(|(ext, delimiter)| basename.strip_suffix(ext).map(|stem| (stem, *delimiter)))
+}
+
+/// Builds csv format from the arguments
+fn csv_format(args: &Args, default_delimiter: u8) -> Format {
+    let mut format = Format::default()
+        .with_header(!args.no_header)
+        .with_delimiter(args.delimiter.unwrap_or(default_delimiter));
+    if let Some(quote) = args.quote {
+        format = format.with_quote(quote);
+    }
+    if let Some(escape) = args.escape {
+        format = format.with_escape(escape);
+    }
+    if let Some(comment) = args.comment {
+        format = format.with_comment(comment);
+    }
+    if let Some(terminator) = args.terminator {
+        format = format.with_terminator(terminator);
+    }
+    format
+}
+
+/// Returns the path of the file csv data is read from. It differs from the file name
+/// for zip archive members.
+fn input_path(filename: &Path) -> &Path {
+    match split_member(filename) {
+        Some((archive, _)) => archive,
+        None => filename,
+    }
+}
+
+/// Returns settings of converting csv data of the file to UTF-8
+fn text_settings(filename: &Path, args: &Args) -> TextSettings {
+    TextSettings {
+        filename: filename.to_str().unwrap().to_string(),
+        encoding: args.encoding.unwrap_or(encoding_rs::UTF_8),
+        invalid_utf8: args.invalid_utf8,
+        records: (args.invalid_utf8 == InvalidUtf8::SkipRow)
+            .then(|| (record_reader(filename, args), !args.no_header)),
+    }
+}
+
+/// Opens csv data of the file converted to UTF-8. The standard input is opened once
+/// and rewound for every pass over it, see [`return_input`].
+fn open_input(
     filename: &Path,
     args: &Args,
-    overrides: &HashMap<String, DataType>,
-    default_int_type: &DataType,
-    default_float_type: &DataType,
-) -> Result<()> {
-    if !filename.is_file() {
-        if !filename.exists() {
-            eprintln!("{} not found", filename.to_str().unwrap());
-        } else {
-            eprintln!("{} is not a file -- skipping", filename.to_str().unwrap());
+    settings: &Settings,
+) -> std::io::Result<RewindableReader> {
+    if is_stdio(filename) {
+        let stdin = settings.stdin.lock().unwrap().take();
+        return stdin
+            .ok_or_else(|| std::io::Error::other("Standard input can be read only once"))?
+            .rewind();
+    }
+    let text = text_settings(filename, args);
+    RewindableReader::open(filename, settings.input_compression, text)
+}
+
+/// Keeps the reader of the standard input for the next pass over it
+fn return_input(filename: &Path, reader: RewindableReader, settings: &Settings) {
+    if is_stdio(filename) {
+        *settings.stdin.lock().unwrap() = Some(reader);
+    }
+}
+
+/// Stops keeping data of the reader before the last pass over it. Data of pipes is kept
+/// with --adaptive to read them again after widening a column.
+fn last_pass(reader: &mut RewindableReader, args: &Args) {
+    if !args.adaptive || reader.seekable() {
+        reader.stop_recording();
+    }
+}
+
+/// Returns the size of csv data of the file. The size of piped data is unknown and 0.
+fn input_size(filename: &Path, settings: &Settings) -> u64 {
+    match split_member(filename) {
+        Some((archive, name)) => member_size(archive, &name).unwrap_or(0),
+        None if is_stdio(filename) => settings.stdin_size,
+        None => file_size(filename),
+    }
+}
+
+/// Replaces zip archives with their csv members. Members outside of the archive are reported
+/// as failed.
+fn expand_archives(filenames: Vec<PathBuf>, summary: &mut Summary) -> Result<Vec<PathBuf>> {
+    let mut expanded = vec![];
+    for filename in filenames {
+        if !is_zip(&filename) || !filename.is_file() {
+            expanded.push(filename);
+            continue;
         }
-        return Ok(());
+        let is_csv = |name: &str| {
+            let basename = name.rsplit('/').next().unwrap();
+            split_extension(basename).is_some()
+        };
+        let (members, outside) = members(&filename, is_csv)
+            .map_err(|err| anyhow!("{}: {err}", filename.to_str().unwrap()))?;
+        if members.is_empty() && outside.is_empty() {
+            // Reported as not a csv file
+            expanded.push(filename);
+            continue;
+        }
+        for name in outside {
+            let member = PathBuf::from(format!("{}/{name}", filename.to_str().unwrap()));
+            let err = anyhow!("Archive member {name} is outside of the archive");
+            eprintln!("Error: {}: {err}", member.to_str().unwrap());
+            summary.add(FileReport::failed(&member, &err));
+        }
+        expanded.extend(members);
+    }
+    Ok(expanded)
+}
+
+/// Splits the file name into a stem and a default delimiter for the extension.
+/// The standard input has the comma delimiter.
+fn input_extension(filename: &Path) -> Option<(&str, u8)> {
+    if is_stdio(filename) {
+        return Some((STDIO, b','));
     }
+    split_extension(filename.file_name()?.to_str().unwrap())
+}
+
+/// Returns the delimiter of a file with the default one for its extension
+fn file_delimiter(filename: &Path, args: &Args) -> u8 {
+    let default_delimiter = input_extension(filename).map_or(b',', |(_, delimiter)| delimiter);
+    args.delimiter.unwrap_or(default_delimiter)
+}
+
+/// Builds a csv_core reader with the same dialect as [`csv_format`]. It finds record ends
+/// when a file is split into chunks parsed in parallel.
+fn record_reader(filename: &Path, args: &Args) -> csv_core::Reader {
+    let mut builder = csv_core::ReaderBuilder::new();
+    builder
+        .delimiter(file_delimiter(filename, args))
+        .escape(args.escape)
+        .comment(args.comment);
+    if let Some(quote) = args.quote {
+        builder.quote(quote);
+    }
+    if let Some(terminator) = args.terminator {
+        builder.terminator(csv_core::Terminator::Any(terminator));
+    }
+    builder.build()
+}
+
+/// Builds csv formats for reading a file and for inferring its schema
+fn csv_formats(filename: &Path, args: &Args, settings: &Settings) -> (Format, Format) {
+    let mut format = csv_format(args, file_delimiter(filename, args));
+    if let Some(regex) = &settings.null_values.regex {
+        format = format.with_null_regex(regex.clone());
+    }
+    let inference_format = match &settings.null_values.inference_regex {
+        Some(regex) => format.clone().with_null_regex(regex.clone()),
+        None => format.clone(),
+    };
+    (format, inference_format)
+}
 
-    let mut reader = RewindableReader::open(filename)?;
+/// Infers the schema of a csv file and applies user-provided data types
+fn infer_file_schema(filename: &Path, args: &Args, settings: &Settings) -> Result<Schema> {
+    let reader = open_input(filename, args, settings)?;
+    let (schema, reader) = infer_schema(reader, filename, args, settings)?;
+    return_input(filename, reader, settings);
+    Ok(schema)
+}
 
-    let format = Format::default().with_header(true).with_delimiter(b',');
-    let (mut schema, _size) = format.infer_schema(&mut reader, Some(MAX_READ_RECORDS))?;
+/// Infers the schema of a csv file from the reader. Returns the schema and the reader
+/// to rewind it for the conversion.
+fn infer_schema(
+    mut reader: RewindableReader,
+    filename: &Path,
+    args: &Args,
+    settings: &Settings,
+) -> Result<(Schema, RewindableReader)> {
+    let (_, format) = csv_formats(filename, args, settings);
+    let max_records = if args.infer_all {
+        None
+    } else {
+        Some(args.infer_rows)
+    };
+    let sample = if args.infer_sample {
+        reader
+            .sample(args.terminator.unwrap_or(b'\n'))
+            .transpose()?
+    } else {
+        None
+    };
+    let (mut schema, _size) = match &sample {
+        Some(sample) => format.infer_schema(sample.as_slice(), None)?,
+        None => format.infer_schema(&mut reader, max_records)?,
+    };
+    if let Some(names) = &args.names {
+        rename_columns(&mut schema, names)?;
+    }
+    // Digits are counted in the same data the schema is inferred from
+    if let Some(columns) = &args.decimal {
+        match &sample {
+            Some(sample) => {
+                infer_decimals(&mut schema, columns, &format, sample.as_slice(), None)?;
+            }
+            None => {
+                reader = reader.rewind()?;
+                infer_decimals(&mut schema, columns, &format, &mut reader, max_records)?;
+            }
+        }
+    }
     apply_schema_overrides(
         &mut schema,
-        overrides,
-        default_int_type.clone(),
-        default_float_type.clone(),
+        &settings.overrides,
+        settings.default_int_type.clone(),
+        settings.default_float_type.clone(),
     )?;
-    if args.print_schema {
-        let json = serde_json::to_string_pretty(&schema)?;
-        let filename = filename.to_str().unwrap();
-        println!("{filename}:\n{json}\n");
-        return Ok(());
-    }
+    settings.timestamps.apply(&mut schema)?;
+    Ok((schema, reader))
+}
 
-    let basename = filename.file_name().unwrap().to_str().unwrap();
-    let mut new_filename: PathBuf = filename.to_path_buf();
-    new_filename.pop();
-    let mut basename = if let Some(basename) = basename.strip_suffix(".csv") {
-        basename.to_string()
-    } else if let Some(basename) = basename.strip_suffix(".csv.gz") {
-        basename.to_string()
+/// Writes csv rows to a parquet writer and returns the number of rows. If `source` is set,
+/// the file name is appended to every row as the last column of the writer's schema.
+/// With more than one thread csv is parsed by a pipeline, see [`read_batches`].
+fn write_batches<W: Write + Send>(
+    reader: &mut RewindableReader,
+    writer: &mut ParquetWriter<W>,
+    format: &Format,
+    records: csv_core::Reader,
+    converter: &Converter,
+    source: Option<(&SchemaRef, &str)>,
+    threads: usize,
+) -> Result<u64> {
+    let schema = converter.schema();
+    let process = |batch: Result<RecordBatch, ArrowError>| -> Result<RecordBatch> {
+        let batch = batch.map_err(|err| describe_parse_error(err, &schema))?;
+        let batch = converter.convert(batch)?;
+        let Some((schema, source)) = source else {
+            return Ok(batch);
+        };
+        let mut columns = batch.columns().to_vec();
+        columns.push(Arc::new(StringArray::from(vec![source; batch.num_rows()])));
+        Ok(RecordBatch::try_new(schema.clone(), columns)?)
+    };
+    let mut rows = 0;
+    let mut write = |batch: RecordBatch| -> Result<()> {
+        writer.write(&batch)?;
+        rows += batch.num_rows() as u64;
+        Ok(())
+    };
+    if threads > 1 {
+        let read_schema = converter.read_schema();
+        read_batches(
+            reader,
+            records,
+            format,
+            read_schema,
+            threads,
+            process,
+            write,
+        )?;
+    } else if let Some(data) = reader.mapped() {
+        // Parses the mapped file without copying it
+        let reader = ReaderBuilder::new(converter.read_schema())
+            .with_format(format.clone())
+            .build_buffered(data)?;
+        for batch in reader {
+            write(process(batch)?)?;
+        }
     } else {
+        let reader = ReaderBuilder::new(converter.read_schema())
+            .with_format(format.clone())
+            .build(reader)?;
+        for batch in reader {
+            write(process(batch)?)?;
+        }
+    }
+    Ok(rows)
+}
+
+/// Writes csv rows to parquet and returns the numbers of rows and row groups
+fn write_parquet(
+    reader: &mut RewindableReader,
+    output: &mut TempFile,
+    format: &Format,
+    records: csv_core::Reader,
+    converter: &Converter,
+    writer_props: WriterProperties,
+    threads: usize,
+) -> Result<(u64, usize)> {
+    let mut writer = ParquetWriter::try_new(output, converter.schema(), writer_props, threads)?;
+    let rows = write_batches(
+        reader,
+        &mut writer,
+        format,
+        records,
+        converter,
+        None,
+        threads,
+    )?;
+    let metadata = writer.close()?;
+    Ok((rows, metadata.row_groups.len()))
+}
+
+/// Returns a copy of the schema with the column widened because of the error,
+/// or `None` if it can't be widened or --adaptive is not set
+fn widen_column(
+    schema: &Schema,
+    err: &anyhow::Error,
+    filename: &Path,
+    args: &Args,
+) -> Option<Schema> {
+    if !args.adaptive {
+        return None;
+    }
+    let column = err.downcast_ref::<ColumnError>()?.column;
+    let field = schema.field(column);
+    let data_type = widen(field.data_type())?;
+    eprintln!(
+        "{}: column `{}' is widened from {} to {data_type}: {err}",
+        filename.to_str().unwrap(),
+        field.name(),
+        field.data_type(),
+    );
+    let mut new_fields: Vec<Field> = schema.fields().iter().map(|f| f.as_ref().clone()).collect();
+    new_fields[column] = new_fields[column].clone().with_data_type(data_type);
+    Some(Schema::new_with_metadata(
+        new_fields,
+        schema.metadata().clone(),
+    ))
+}
+
+/// Removes input files if --rm is set. Archive members are removed with their archives,
+/// see [`remove_archives`].
+fn remove_inputs(filenames: &[&Path], args: &Args) {
+    if !args.rm {
+        return;
+    }
+    for filename in filenames
+        .iter()
+        .filter(|filename| !is_stdio(filename) && split_member(filename).is_none())
+    {
+        remove_input(filename);
+    }
+}
+
+/// Removes zip archives if --rm is set and all their csv members are converted
+fn remove_archives(archives: &[PathBuf], filenames: &[PathBuf], summary: &Summary, args: &Args) {
+    if !args.rm || args.print_schema {
+        return;
+    }
+    let in_archive = |filename: &Path, archive: &Path| {
+        split_member(filename).is_some_and(|(path, _)| path == archive)
+    };
+    for archive in archives {
+        let members = filenames
+            .iter()
+            .filter(|filename| in_archive(filename, archive))
+            .count();
+        let reports: Vec<&FileReport> = summary
+            .files
+            .iter()
+            .filter(|report| in_archive(Path::new(&report.filename), archive))
+            .collect();
+        if members == 0 {
+            continue;
+        }
+        if reports.len() == members
+            && reports
+                .iter()
+                .all(|report| report.status == Status::Converted)
+        {
+            remove_input(archive);
+        } else {
+            eprintln!(
+                "{} is kept because not all of its csv members are converted",
+                archive.to_str().unwrap()
+            );
+        }
+    }
+}
+
+fn remove_input(filename: &Path) {
+    if let Err(err) = remove_file(filename) {
         eprintln!(
-            "{} is not a csv[.gz] file -- skipping",
+            "Can't remove original file {}: {err}",
             filename.to_str().unwrap()
         );
-        return Ok(());
+    }
+}
+
+/// Reports a skipped file
+fn skip(filename: &Path, reason: String, summary: &mut Summary) {
+    eprintln!("{reason} -- skipping");
+    summary.add(FileReport::skipped(filename, reason));
+}
+
+/// Writes all csv files into a single parquet file
+fn combine(
+    filenames: &[PathBuf],
+    output: &Path,
+    args: &Args,
+    settings: &Settings,
+    summary: &mut Summary,
+) -> Result<()> {
+    let mut inputs: Vec<&Path> = vec![];
+    for filename in filenames {
+        let path = input_path(filename);
+        if is_stdio(filename) {
+            inputs.push(filename);
+        } else if !path.exists() {
+            let err = anyhow!("{} not found", filename.to_str().unwrap());
+            if !args.keep_going {
+                return Err(err);
+            }
+            eprintln!("Error: {err}");
+            summary.add(FileReport::failed(filename, &err));
+        } else if !path.is_file() {
+            let reason = format!("{} is not a file", filename.to_str().unwrap());
+            skip(filename, reason, summary);
+        } else if input_extension(filename).is_none() {
+            let reason = format!("{} is not a csv/tsv/psv file", filename.to_str().unwrap());
+            skip(filename, reason, summary);
+        } else {
+            inputs.push(filename);
+        }
+    }
+    if inputs.is_empty() {
+        return Err(anyhow!(
+            "No csv files to write to {}",
+            output.to_str().unwrap()
+        ));
+    }
+
+    let mut schema = match &settings.explicit_schema {
+        Some(schema) => schema.clone(),
+        None => {
+            let schemas = inputs
+                .iter()
+                .map(|filename| infer_file_schema(filename, args, settings))
+                .collect::<Result<Vec<_>>>()?;
+            merge_schemas(&schemas)?
+        }
     };
-    basename.push_str(".parquet");
-    let tmp_basename = String::from(".tmp.") + &basename;
-    let mut tmp_filename = new_filename.clone();
-    new_filename.push(basename);
-    tmp_filename.push(tmp_basename);
-    if new_filename.exists() {
-        eprintln!(
-            "{} is already exists -- skipping",
-            new_filename.to_str().unwrap()
-        );
+    for filename in &inputs {
+        let (format, _) = csv_formats(filename, args, settings);
+        let mut reader = open_input(filename, args, settings)?;
+        // Reads the header only
+        let (header, _size) = format.infer_schema(&mut reader, Some(0))?;
+        check_header(&header, &schema, !args.no_header && args.names.is_none())
+            .map_err(|err| anyhow!("{}: {err}", filename.to_str().unwrap()))?;
+        return_input(filename, reader, settings);
+    }
+    settings.null_values.check_columns(&schema)?;
+    let output_schema = |schema: &Schema| -> Result<Schema> {
+        let Some(source_column) = &args.source_column else {
+            return Ok(schema.clone());
+        };
+        if schema.field_with_name(source_column).is_ok() {
+            return Err(anyhow!("Column `{source_column}' already exists"));
+        }
+        let mut fields: Vec<Field> = schema.fields().iter().map(|f| f.as_ref().clone()).collect();
+        fields.push(Field::new(source_column, DataType::Utf8, false));
+        Ok(Schema::new_with_metadata(fields, schema.metadata().clone()))
+    };
+    if args.print_schema {
+        let json = serde_json::to_string_pretty(&output_schema(&schema)?)?;
+        println!("{}:\n{json}\n", output.to_str().unwrap());
         return Ok(());
     }
-    if tmp_filename.exists() {
-        eprintln!(
-            "Temporary filename {} is already exists -- skipping",
-            tmp_filename.to_str().unwrap()
-        );
+
+    let paths: Vec<&Path> = inputs.iter().map(|filename| input_path(filename)).collect();
+    if let Some(reason) = check_output(&paths, output, args.if_exists, args.clean_stale_tmp)? {
+        for filename in &inputs {
+            skip(filename, reason.clone(), summary);
+        }
         return Ok(());
     }
-    let mut output = TempFile::create_new(tmp_filename.into_os_string().into_string().unwrap())?;
-    if std::io::stdin().is_terminal() {
-        println!("{}", filename.to_str().unwrap());
+    let mut output_file = create_output(output)?;
+    let inferred_schema = schema.clone();
+    let mut rows = vec![];
+    'restart: loop {
+        rows.clear();
+        let converter = Converter::new(
+            Arc::new(schema.clone()),
+            &settings.timestamps,
+            &settings.null_values,
+            args.decimal_excess,
+        );
+        let full_schema = Arc::new(output_schema(&schema)?);
+        let writer_props = settings.writer.build(&full_schema)?;
+        let mut writer = ParquetWriter::try_new(
+            &mut output_file,
+            full_schema.clone(),
+            writer_props,
+            args.threads,
+        )?;
+        for filename in &inputs {
+            if std::io::stdin().is_terminal() && !is_stdio(output) {
+                println!("{}", filename.to_str().unwrap());
+            }
+            let (format, _) = csv_formats(filename, args, settings);
+            let records = record_reader(filename, args);
+            let mut reader = open_input(filename, args, settings)?;
+            last_pass(&mut reader, args);
+            let source = args
+                .source_column
+                .as_ref()
+                .map(|_| (&full_schema, filename.to_str().unwrap()));
+            let written = write_batches(
+                &mut reader,
+                &mut writer,
+                &format,
+                records,
+                &converter,
+                source,
+                args.threads,
+            );
+            return_input(filename, reader, settings);
+            match written {
+                Ok(file_rows) => rows.push(file_rows),
+                // Named pipes are opened again on restarts
+                Err(err)
+                    if inputs
+                        .iter()
+                        .any(|input| !is_stdio(input) && !input_path(input).is_file()) =>
+                {
+                    return Err(err);
+                }
+                Err(err) => match widen_column(&schema, &err, filename, args) {
+                    Some(widened) => {
+                        schema = widened;
+                        drop(writer);
+                        output_file.reset()?;
+                        continue 'restart;
+                    }
+                    _ => return Err(err),
+                },
+            }
+        }
+        writer.close()?;
+        break;
     }
-    let schema_ref = Arc::new(schema);
-    let reader = ReaderBuilder::new(schema_ref)
-        .with_format(format)
-        .build(reader.rewind()?)?;
-
-    let writer_props = WriterProperties::builder()
-        .set_compression(Compression::GZIP(GzipLevel::try_new(8).unwrap()));
-    let mut writer =
-        ArrowWriter::try_new(&mut output, reader.schema(), Some(writer_props.build()))?;
-    for batch in reader {
-        let batch = batch?;
-        writer.write(&batch)?;
+    summary.bytes_out += publish(output_file, output)?;
+    for (filename, rows) in inputs.iter().zip(rows) {
+        let mut report =
+            FileReport::converted(filename, output, rows, input_size(filename, settings));
+        report.inferred_schema = Some(inferred_schema.clone());
+        report.schema = Some(schema.clone());
+        summary.add(report);
     }
-    writer.close()?;
-    output.flush_and_rename(new_filename)?;
-    if args.rm {
-        if let Err(err) = remove_file(filename) {
-            eprintln!(
-                "Can't remove original file {}: {err}",
-                filename.to_str().unwrap()
-            );
+    remove_inputs(&inputs, args);
+    Ok(())
+}
+
+/// Returns the directory of the parquet file converted from the csv file
+fn output_directory(filename: &Path, args: &Args) -> Result<PathBuf> {
+    let Some(output_dir) = &args.output_dir else {
+        let mut directory = filename.to_path_buf();
+        directory.pop();
+        return Ok(directory);
+    };
+    let Some(base_dir) = &args.base_dir else {
+        return Ok(output_dir.clone());
+    };
+    let directory = filename.canonicalize()?.parent().unwrap().to_path_buf();
+    let relative = directory
+        .strip_prefix(base_dir.canonicalize()?)
+        .map_err(|_| {
+            anyhow!(
+                "{} is not in the base directory {}",
+                filename.to_str().unwrap(),
+                base_dir.to_str().unwrap()
+            )
+        })?;
+    Ok(output_dir.join(relative))
+}
+
+/// Returns the name of the Parquet file a csv file is converted to
+fn output_filename(filename: &Path, args: &Args) -> Result<PathBuf> {
+    // The standard input is written to the standard output
+    if is_stdio(filename) {
+        return Ok(PathBuf::from(STDIO));
+    }
+    let (stem, _) = input_extension(filename)
+        .ok_or_else(|| anyhow!("{} is not a csv/tsv/psv file", filename.to_str().unwrap()))?;
+    let mut new_filename = output_directory(input_path(filename), args)?;
+    // Archive members are written next to the archive keeping their directories
+    if let Some((_, name)) = split_member(filename)
+        && let Some((directory, _)) = name.rsplit_once('/')
+    {
+        let directory = Path::new(directory);
+        if !directory
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+        {
+            return Err(anyhow!("Archive member {name} is outside of the archive"));
+        }
+        new_filename.push(directory);
+    }
+    new_filename.push(stem.to_string() + ".parquet");
+    Ok(new_filename)
+}
+
+/// Fails if several csv files would be converted to the same Parquet file
+fn check_output_names(filenames: &[PathBuf], args: &Args) -> Result<()> {
+    let mut outputs: HashMap<PathBuf, &Path> = HashMap::new();
+    for filename in filenames {
+        // Other files are skipped or fail on their own
+        let Ok(output) = output_filename(filename, args) else {
+            continue;
+        };
+        if let Some(other) = outputs.insert(std::path::absolute(&output)?, filename) {
+            return Err(anyhow!(
+                "{} and {} are both converted to {}",
+                other.to_str().unwrap(),
+                filename.to_str().unwrap(),
+                output.to_str().unwrap()
+            ));
         }
     }
     Ok(())
 }
 
-fn main() -> Result<()> {
+/// Converts a single csv file to parquet
+fn process(filename: &Path, args: &Args, settings: &Settings, summary: &mut Summary) -> Result<()> {
+    let path = input_path(filename);
+    if !is_stdio(filename) && !path.exists() {
+        return Err(anyhow!("not found"));
+    }
+    // Pipes are read once, so they can be converted as well
+    if path.is_dir() {
+        let reason = format!("{} is not a file", filename.to_str().unwrap());
+        skip(filename, reason, summary);
+        return Ok(());
+    }
+
+    let extension = input_extension(filename);
+    if extension.is_none() && !args.print_schema {
+        let reason = format!("{} is not a csv/tsv/psv file", filename.to_str().unwrap());
+        skip(filename, reason, summary);
+        return Ok(());
+    }
+    let (format, _) = csv_formats(filename, args, settings);
+
+    let mut reader = open_input(filename, args, settings)?;
+    let (mut schema, reader) = if let Some(schema) = &settings.explicit_schema {
+        // Reads the header only
+        let (header, _size) = format.infer_schema(&mut reader, Some(0))?;
+        check_header(&header, schema, !args.no_header && args.names.is_none())?;
+        (schema.clone(), reader)
+    } else {
+        infer_schema(reader, filename, args, settings)?
+    };
+    settings.null_values.check_columns(&schema)?;
+    let mut writer_props = settings.writer.build(&schema)?;
+    let new_converter = |schema: &Schema| {
+        Converter::new(
+            Arc::new(schema.clone()),
+            &settings.timestamps,
+            &settings.null_values,
+            args.decimal_excess,
+        )
+    };
+    let mut converter = new_converter(&schema);
+    if args.print_schema {
+        let json = serde_json::to_string_pretty(&schema)?;
+        let filename = filename.to_str().unwrap();
+        println!("{filename}:\n{json}\n");
+        return Ok(());
+    }
+
+    let new_filename = output_filename(filename, args)?;
+    if let Some(reason) =
+        check_output(&[path], &new_filename, args.if_exists, args.clean_stale_tmp)?
+    {
+        skip(filename, reason, summary);
+        return Ok(());
+    }
+    if (args.output_dir.is_some() || split_member(filename).is_some()) && !is_stdio(&new_filename) {
+        create_dir_all(new_filename.parent().unwrap())?;
+    }
+    let mut output = create_output(&new_filename)?;
+    if std::io::stdin().is_terminal() && !is_stdio(&new_filename) {
+        println!("{}", filename.to_str().unwrap());
+    }
+    // Data read for the inference is replayed from memory
+    let mut reader = reader.rewind()?;
+    last_pass(&mut reader, args);
+    let inferred_schema = schema.clone();
+    let (rows, row_groups) = loop {
+        let records = record_reader(filename, args);
+        let written = write_parquet(
+            &mut reader,
+            &mut output,
+            &format,
+            records,
+            &converter,
+            writer_props,
+            args.threads,
+        );
+        let err = match written {
+            Ok(written) => break written,
+            Err(err) => err,
+        };
+        match widen_column(&schema, &err, filename, args) {
+            Some(widened) => schema = widened,
+            _ => return Err(err),
+        }
+        writer_props = settings.writer.build(&schema)?;
+        converter = new_converter(&schema);
+        output.reset()?;
+        reader = reader.rewind()?;
+    };
+    let bytes_out = publish(output, &new_filename)?;
+    let mut report = FileReport::converted(
+        filename,
+        &new_filename,
+        rows,
+        input_size(filename, settings),
+    );
+    report.inferred_schema = Some(inferred_schema);
+    report.schema = Some(schema);
+    report.row_groups = Some(row_groups);
+    report.bytes_out = Some(bytes_out);
+    summary.add(report);
+    remove_inputs(&[filename], args);
+    Ok(())
+}
+
+/// Converts csv files to parquet with `jobs` threads. Returns summaries of processed files
+/// in the order of files.
+fn process_all(
+    filenames: &[PathBuf],
+    args: &Args,
+    settings: &Settings,
+    jobs: usize,
+) -> Vec<Summary> {
+    let next = AtomicUsize::new(0);
+    let failed = AtomicBool::new(false);
+    let summaries: Vec<Mutex<Option<Summary>>> =
+        filenames.iter().map(|_| Mutex::new(None)).collect();
+    thread::scope(|scope| {
+        for _ in 0..jobs.min(filenames.len()) {
+            scope.spawn(|| {
+                while args.keep_going || !failed.load(Ordering::Relaxed) {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(filename) = filenames.get(i) else {
+                        break;
+                    };
+                    let mut summary = Summary::default();
+                    if let Err(err) = process(filename, args, settings, &mut summary) {
+                        eprintln!("Error: {}: {err:#}", filename.to_str().unwrap());
+                        summary.add(FileReport::failed(filename, &err));
+                        failed.store(true, Ordering::Relaxed);
+                    }
+                    *summaries[i].lock().unwrap() = Some(summary);
+                }
+            });
+        }
+    });
+    summaries
+        .into_iter()
+        .filter_map(|summary| summary.into_inner().unwrap())
+        .collect()
+}
+
+/// Prints the summary, writes the report and returns the exit code
+fn finish(summary: &Summary, args: &Args) -> Result<ExitCode> {
+    if !args.print_schema {
+        summary.print();
+    }
+    if let Some(report) = &args.report {
+        let json = serde_json::to_string_pretty(&summary.to_json())?;
+        std::fs::write(report, json + "\n")?;
+    }
+    Ok(summary.exit_code())
+}
+
+fn main() -> Result<ExitCode> {
     let mut args: Args = Args::parse();
-    let filenames = args.input;
-    args.input = vec![];
-    let (overrides, default_int_type, default_float_type) = consolidate_types(&mut args)?;
-    for filename in &filenames {
-        process(
-            filename,
-            &args,
-            &overrides,
-            &default_int_type,
-            &default_float_type,
-        )?;
+    let mut summary = Summary::default();
+    if let Err(err) = run(&mut args, &mut summary) {
+        eprintln!("Error: {err:#}");
+        summary.fail(&err);
+    }
+    finish(&summary, &args)
+}
+
+/// Converts all files adding their reports to the summary. Returns errors which stop the run.
+fn run(args: &mut Args, summary: &mut Summary) -> Result<()> {
+    let archives: Vec<PathBuf> = args
+        .input
+        .iter()
+        .filter(|filename| is_zip(filename) && filename.is_file())
+        .cloned()
+        .collect();
+    let filenames = expand_archives(std::mem::take(&mut args.input), summary)?;
+    if !args.keep_going && summary.count(Status::Failed) > 0 {
+        return Ok(());
+    }
+    let (overrides, default_int_type, default_float_type) = consolidate_types(args)?;
+    let cpus = available_parallelism().map_or(1, NonZeroUsize::get);
+    if args.threads == 0 {
+        args.threads = cpus;
+    }
+    let mut settings = Settings {
+        overrides,
+        default_int_type,
+        default_float_type,
+        explicit_schema: args.schema.as_deref().map(load_schema).transpose()?,
+        timestamps: TimestampSettings::new(
+            std::mem::take(&mut args.timestamp_format),
+            std::mem::take(&mut args.epoch),
+            args.timezone.take(),
+            args.timestamp_unit,
+        )?,
+        null_values: NullValues::new(
+            &args.null_values,
+            std::mem::take(&mut args.column_null_values),
+        )?,
+        writer: WriterSettings {
+            compression: args.compression,
+            column_encodings: std::mem::take(&mut args.column_encoding),
+            no_dictionary: std::mem::take(&mut args.no_dictionary),
+            column_compressions: std::mem::take(&mut args.column_compression),
+        },
+        stdin: Mutex::new(None),
+        stdin_size: 0,
+        input_compression: args.input_compression,
+    };
+    match filenames
+        .iter()
+        .filter(|filename| is_stdio(filename))
+        .count()
+    {
+        0 => {}
+        1 => {
+            let text = text_settings(Path::new(STDIO), args);
+            settings.stdin_size = stdin_size();
+            let stdin = RewindableReader::stdin(args.input_compression, text)?;
+            settings.stdin = Mutex::new(Some(stdin));
+        }
+        _ => return Err(anyhow!("Standard input can be read only once")),
+    }
+    if let Some(output) = &args.output {
+        if let Err(err) = combine(&filenames, output, args, &settings, summary) {
+            eprintln!("Error: {err:#}");
+            summary.add(FileReport::failed(output, &err));
+        }
+        remove_archives(&archives, &filenames, summary, args);
+        return Ok(());
+    }
+    if !args.print_schema {
+        check_output_names(&filenames, args)?;
+    }
+    if args.unify_schema {
+        let schemas = filenames
+            .iter()
+            .filter(|filename| {
+                (is_stdio(filename) || input_path(filename).is_file())
+                    && input_extension(filename).is_some()
+            })
+            .map(|filename| infer_file_schema(filename, args, &settings))
+            .collect::<Result<Vec<_>>>()?;
+        settings.explicit_schema = Some(merge_schemas(&schemas)?);
+    }
+    let jobs = match args.jobs {
+        0 => cpus,
+        jobs => jobs,
+    };
+    for file_summary in process_all(&filenames, args, &settings, jobs) {
+        summary.extend(file_summary);
     }
+    remove_archives(&archives, &filenames, summary, args);
     Ok(())
 }
@@ -0,0 +1,119 @@
+use std::{
+    fs::remove_file,
+    io::stdout,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Result, anyhow};
+use clap::ValueEnum;
+
+use crate::{
+    stdio::{is_stdio, stdout_tmp_file},
+    tempfile::TempFile,
+};
+
+/// What to do if the Parquet file already exists
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum IfExists {
+    /// Keep the existing file
+    Skip,
+    /// Replace the existing file
+    Overwrite,
+    /// Stop with an error
+    Fail,
+    /// Replace the existing file if a csv file was modified after it
+    Newer,
+}
+
+/// Returns the name of the temporary file the Parquet file is written to
+fn tmp_filename(filename: &Path) -> PathBuf {
+    let basename = filename.file_name().unwrap().to_str().unwrap();
+    filename.with_file_name(String::from(".tmp.") + basename)
+}
+
+/// Creates the temporary file the Parquet file is written to
+pub fn create_output(output: &Path) -> std::io::Result<TempFile> {
+    if is_stdio(output) {
+        stdout_tmp_file()
+    } else {
+        let tmp_filename = tmp_filename(output);
+        TempFile::create_new(tmp_filename.into_os_string().into_string().unwrap())
+    }
+}
+
+/// Renames the written temporary file to the Parquet file or copies it to the standard output.
+/// Returns the size of the Parquet file.
+pub fn publish(file: TempFile, output: &Path) -> std::io::Result<u64> {
+    if is_stdio(output) {
+        file.copy_to(&mut stdout())
+    } else {
+        file.flush_and_rename(output)?;
+        Ok(output.metadata()?.len())
+    }
+}
+
+/// Returns `true` if any of the csv files was modified after the Parquet file
+fn is_outdated(inputs: &[&Path], output: &Path) -> Result<bool> {
+    let modified = output.metadata()?.modified()?;
+    // The standard input is always new
+    if inputs.iter().any(|input| is_stdio(input)) {
+        return Ok(true);
+    }
+    for input in inputs {
+        if input.metadata()?.modified()? > modified {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Checks whether the Parquet file converted from the csv files should be written.
+/// Returns the reason if it should be skipped. The standard output is always written.
+pub fn check_output(
+    inputs: &[&Path],
+    output: &Path,
+    if_exists: IfExists,
+    clean_stale_tmp: bool,
+) -> Result<Option<String>> {
+    if is_stdio(output) {
+        return Ok(None);
+    }
+    let tmp_filename = tmp_filename(output);
+    if tmp_filename.exists() {
+        if clean_stale_tmp {
+            remove_file(&tmp_filename)?;
+            eprintln!(
+                "Stale temporary file {} is removed",
+                tmp_filename.to_str().unwrap()
+            );
+        } else if if_exists == IfExists::Fail {
+            return Err(anyhow!(
+                "Temporary file {} already exists",
+                tmp_filename.to_str().unwrap()
+            ));
+        } else {
+            return Ok(Some(format!(
+                "Temporary filename {} is already exists",
+                tmp_filename.to_str().unwrap()
+            )));
+        }
+    }
+    if !output.exists() {
+        return Ok(None);
+    }
+    match if_exists {
+        IfExists::Skip => Ok(Some(format!(
+            "{} is already exists",
+            output.to_str().unwrap()
+        ))),
+        IfExists::Overwrite => Ok(None),
+        IfExists::Fail => Err(anyhow!("{} already exists", output.to_str().unwrap())),
+        IfExists::Newer => {
+            if is_outdated(inputs, output)? {
+                Ok(None)
+            } else {
+                Ok(Some(format!("{} is up to date", output.to_str().unwrap())))
+            }
+        }
+    }
+}
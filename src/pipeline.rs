@@ -0,0 +1,349 @@
+use std::{
+    borrow::Cow,
+    collections::BTreeMap,
+    io::Read,
+    sync::{Arc, Mutex, mpsc},
+    thread,
+};
+
+use anyhow::Result;
+use arrow_array::RecordBatch;
+use arrow_csv::{ReaderBuilder, reader::Format};
+use arrow_schema::{ArrowError, SchemaRef};
+use csv_core::{ReadRecordResult, Reader};
+use regex::{Captures, Regex};
+
+use crate::rewindable_reader::RewindableReader;
+
+/// Approximate size of a chunk of csv records parsed by a thread
+pub const CHUNK_SIZE: usize = 4 << 20;
+
+/// Chunk of whole csv records
+struct Chunk<'a> {
+    index: usize,
+    /// Data read from the input or a range of a mapped file
+    data: Cow<'a, [u8]>,
+    /// Number of records before the chunk including the header
+    first_line: usize,
+}
+
+/// Adds the number of records before a chunk to line numbers in an error message
+fn shift_line_numbers(err: ArrowError, first_line: usize) -> ArrowError {
+    let regex = Regex::new(r"\bline (\d+)").unwrap();
+    let shift = |message: String| {
+        regex
+            .replace_all(&message, |captures: &Captures| {
+                let line: usize = captures[1].parse().unwrap();
+                format!("line {}", line + first_line)
+            })
+            .into_owned()
+    };
+    match err {
+        ArrowError::ParseError(message) => ArrowError::ParseError(shift(message)),
+        ArrowError::CsvError(message) => ArrowError::CsvError(shift(message)),
+        err => err,
+    }
+}
+
+/// Splits csv data into chunks of whole records using `records` to find record ends
+fn split_records(
+    mut input: impl Read,
+    mut records: Reader,
+    sender: mpsc::SyncSender<Chunk<'_>>,
+) -> Result<()> {
+    let mut output = vec![0; 1 << 16];
+    let mut ends = vec![0; 1 << 10];
+    let mut data: Vec<u8> = Vec::with_capacity(CHUNK_SIZE * 2);
+    // Bytes of `data` scanned for record ends
+    let mut scanned = 0;
+    // End of the last complete record in `data` and the number of records before it
+    let mut last_end = 0;
+    let mut last_records = 0;
+    let mut first_line = 0;
+    let mut index = 0;
+    let mut eof = false;
+    while !eof {
+        let length = data.len();
+        data.resize(length + CHUNK_SIZE, 0);
+        let read = input.read(&mut data[length..])?;
+        data.truncate(length + read);
+        eof = read == 0;
+        // Empty input means the end of data for the csv reader
+        while scanned < data.len() || eof {
+            let (result, bytes, _, _) =
+                records.read_record(&data[scanned..], &mut output, &mut ends);
+            scanned += bytes;
+            match result {
+                ReadRecordResult::Record => {
+                    last_end = scanned;
+                    last_records += 1;
+                }
+                ReadRecordResult::InputEmpty | ReadRecordResult::End => break,
+                ReadRecordResult::OutputFull | ReadRecordResult::OutputEndsFull => {}
+            }
+        }
+        if (last_end >= CHUNK_SIZE || eof) && last_end > 0 {
+            let rest = data.split_off(last_end);
+            let chunk = Chunk {
+                index,
+                data: Cow::Owned(std::mem::replace(&mut data, rest)),
+                first_line,
+            };
+            if sender.send(chunk).is_err() {
+                // The conversion failed
+                return Ok(());
+            }
+            index += 1;
+            first_line += last_records;
+            scanned -= last_end;
+            last_end = 0;
+            last_records = 0;
+        }
+    }
+    if !data.is_empty() {
+        let _ = sender.send(Chunk {
+            index,
+            data: Cow::Owned(data),
+            first_line,
+        });
+    }
+    Ok(())
+}
+
+/// Splits mapped csv data into byte ranges of whole records without copying it
+fn split_ranges<'a>(
+    data: &'a [u8],
+    mut records: Reader,
+    sender: mpsc::SyncSender<Chunk<'a>>,
+) -> Result<()> {
+    let mut output = vec![0; 1 << 16];
+    let mut ends = vec![0; 1 << 10];
+    // Start of the chunk in progress, bytes scanned for record ends and records in the chunk
+    let mut start = 0;
+    let mut scanned = 0;
+    let mut chunk_records = 0;
+    let mut first_line = 0;
+    let mut index = 0;
+    loop {
+        // Empty input at the end of data means the end of data for the csv reader
+        let (result, bytes, _, _) = records.read_record(&data[scanned..], &mut output, &mut ends);
+        scanned += bytes;
+        let end = match result {
+            ReadRecordResult::Record if scanned - start >= CHUNK_SIZE => scanned,
+            ReadRecordResult::Record => {
+                chunk_records += 1;
+                continue;
+            }
+            ReadRecordResult::End => data.len(),
+            _ => continue,
+        };
+        if end > start {
+            let chunk = Chunk {
+                index,
+                data: Cow::Borrowed(&data[start..end]),
+                first_line,
+            };
+            if sender.send(chunk).is_err() {
+                // The conversion failed
+                return Ok(());
+            }
+        }
+        if result == ReadRecordResult::End {
+            return Ok(());
+        }
+        index += 1;
+        first_line += chunk_records + 1;
+        chunk_records = 0;
+        start = end;
+    }
+}
+
+/// Reads csv batches with a pipeline: a thread splits the input into chunks of whole records,
+/// `threads` threads parse chunks and pass batches through `process`. `write` is called for
+/// batches in the order of the input. Chunks of mapped files are parsed in place.
+pub fn read_batches(
+    input: &mut RewindableReader,
+    records: Reader,
+    format: &Format,
+    schema: SchemaRef,
+    threads: usize,
+    process: impl Fn(Result<RecordBatch, ArrowError>) -> Result<RecordBatch> + Sync,
+    write: impl FnMut(RecordBatch) -> Result<()>,
+) -> Result<()> {
+    match input.mapped() {
+        Some(data) => parse_chunks(
+            |sender| split_ranges(data, records, sender),
+            format,
+            schema,
+            threads,
+            process,
+            write,
+        ),
+        None => parse_chunks(
+            |sender| split_records(input, records, sender),
+            format,
+            schema,
+            threads,
+            process,
+            write,
+        ),
+    }
+}
+
+/// Runs the pipeline of [`read_batches`] with `split` sending chunks of the input
+fn parse_chunks<'a>(
+    split: impl FnOnce(mpsc::SyncSender<Chunk<'a>>) -> Result<()> + Send,
+    format: &Format,
+    schema: SchemaRef,
+    threads: usize,
+    process: impl Fn(Result<RecordBatch, ArrowError>) -> Result<RecordBatch> + Sync,
+    mut write: impl FnMut(RecordBatch) -> Result<()>,
+) -> Result<()> {
+    let (chunk_sender, chunk_receiver) = mpsc::sync_channel::<Chunk>(threads);
+    let (batch_sender, batch_receiver) = mpsc::sync_channel(threads);
+    // Dropped with the last parsing thread, which stops the splitting thread
+    let chunk_receiver = Arc::new(Mutex::new(chunk_receiver));
+    let tail_format = format.clone().with_header(false);
+    thread::scope(|scope| {
+        let splitter = scope.spawn(move || split(chunk_sender));
+        for _ in 0..threads {
+            let batch_sender = batch_sender.clone();
+            let chunk_receiver = chunk_receiver.clone();
+            let (schema, process, tail_format) = (&schema, &process, &tail_format);
+            scope.spawn(move || {
+                loop {
+                    let Ok(chunk) = chunk_receiver.lock().unwrap().recv() else {
+                        break;
+                    };
+                    // Only the first chunk contains the header
+                    let format = if chunk.index == 0 {
+                        format
+                    } else {
+                        tail_format
+                    };
+                    let batches =
+                        ReaderBuilder::new(schema.clone())
+                            .with_format(format.clone())
+                            .build_buffered(chunk.data.as_ref())
+                            .map_err(anyhow::Error::from)
+                            .and_then(|reader| {
+                                reader
+                                    .map(|batch| {
+                                        process(batch.map_err(|err| {
+                                            shift_line_numbers(err, chunk.first_line)
+                                        }))
+                                    })
+                                    .collect::<Result<Vec<_>>>()
+                            });
+                    if batch_sender.send((chunk.index, batches)).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(batch_sender);
+        drop(chunk_receiver);
+
+        // Chunks parsed ahead of the next one
+        let mut parsed = BTreeMap::new();
+        let mut next = 0;
+        let result = (|| {
+            for (index, batches) in batch_receiver.iter() {
+                parsed.insert(index, batches);
+                while let Some(batches) = parsed.remove(&next) {
+                    for batch in batches? {
+                        write(batch)?;
+                    }
+                    next += 1;
+                }
+            }
+            Ok(())
+        })();
+        drop(batch_receiver);
+        drop(parsed);
+        let split = splitter.join().unwrap();
+        result.and(split)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use arrow_array::{Array, StringArray};
+    use arrow_schema::{DataType, Field, Schema};
+
+    use super::*;
+
+    /// Returns csv data of about `size` bytes with quoted newlines in every row
+    fn multiline_csv(size: usize) -> Vec<u8> {
+        let mut data = b"id,note,value\n".to_vec();
+        let mut id = 0;
+        while data.len() < size {
+            data.extend_from_slice(format!("{id},\"first\nsecond, {id}\",{}\n", id * 3).as_bytes());
+            id += 1;
+        }
+        data
+    }
+
+    fn schema() -> SchemaRef {
+        Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Utf8, false),
+            Field::new("note", DataType::Utf8, false),
+            Field::new("value", DataType::Utf8, false),
+        ]))
+    }
+
+    /// Returns the values of string batches row by row
+    fn rows(batches: &[RecordBatch]) -> Vec<Vec<String>> {
+        let mut rows = vec![];
+        for batch in batches {
+            for row in 0..batch.num_rows() {
+                let values = batch.columns().iter().map(|column| {
+                    let column = column.as_any().downcast_ref::<StringArray>().unwrap();
+                    column.value(row).to_string()
+                });
+                rows.push(values.collect());
+            }
+        }
+        rows
+    }
+
+    fn parse(data: &[u8], threads: usize, mapped: bool) -> Vec<Vec<String>> {
+        let format = Format::default().with_header(true);
+        let mut batches = vec![];
+        let write = |batch| {
+            batches.push(batch);
+            Ok(())
+        };
+        let process = |batch: Result<RecordBatch, ArrowError>| Ok(batch?);
+        let records = Reader::new();
+        if mapped {
+            let split = |sender| split_ranges(data, records, sender);
+            parse_chunks(split, &format, schema(), threads, process, write).unwrap();
+        } else {
+            let split = |sender| split_records(Cursor::new(data), records, sender);
+            parse_chunks(split, &format, schema(), threads, process, write).unwrap();
+        }
+        rows(&batches)
+    }
+
+    #[test]
+    fn threads_parse_the_same_rows() {
+        let data = multiline_csv(CHUNK_SIZE * 5 / 2);
+        let reader = ReaderBuilder::new(schema())
+            .with_format(Format::default().with_header(true))
+            .build_buffered(data.as_slice())
+            .unwrap();
+        let expected = rows(&reader.collect::<Result<Vec<_>, _>>().unwrap());
+        assert_eq!(expected[1], ["1", "first\nsecond, 1", "3"]);
+        for threads in [1, 4] {
+            assert_eq!(parse(&data, threads, false), expected, "{threads} threads");
+            assert_eq!(
+                parse(&data, threads, true),
+                expected,
+                "{threads} threads, mapped"
+            );
+        }
+    }
+}
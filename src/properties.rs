@@ -0,0 +1,120 @@
+use std::str::FromStr;
+
+use anyhow::{Result, anyhow};
+use arrow_schema::Schema;
+use parquet::{
+    arrow::ArrowSchemaConverter,
+    basic::{Compression, Encoding, Type as PhysicalType},
+    file::properties::WriterProperties,
+    schema::types::ColumnPath,
+};
+
+use crate::compression::parse_compression;
+
+/// Splits a `COLUMN=VALUE` specification. `value` names the value in errors.
+pub fn split_column_spec<'a>(spec: &'a str, value: &str) -> Result<(String, &'a str)> {
+    match spec.split_once('=') {
+        Some((column, v)) if !column.is_empty() && !v.is_empty() => Ok((column.to_string(), v)),
+        _ => Err(anyhow!("Expected COLUMN={value}, got `{spec}'")),
+    }
+}
+
+/// Parses a `COLUMN=ENCODING` specification, e.g. `id=DELTA_BINARY_PACKED`
+pub fn parse_column_encoding(spec: &str) -> Result<(String, Encoding)> {
+    let (column, encoding) = split_column_spec(spec, "ENCODING")?;
+    let encoding = Encoding::from_str(&encoding.to_ascii_uppercase())?;
+    if matches!(
+        encoding,
+        Encoding::PLAIN_DICTIONARY | Encoding::RLE_DICTIONARY
+    ) {
+        return Err(anyhow!(
+            "Dictionary encoding can't be set explicitly, it is enabled by default"
+        ));
+    }
+    Ok((column, encoding))
+}
+
+/// Parses a `COLUMN=CODEC[:LEVEL]` specification, e.g. `blob=zstd:15`
+pub fn parse_column_compression(spec: &str) -> Result<(String, Compression)> {
+    let (column, compression) = split_column_spec(spec, "CODEC[:LEVEL]")?;
+    Ok((column, parse_compression(compression)?))
+}
+
+/// Checks if the encoding can be used for the parquet physical type
+fn is_encoding_supported(encoding: Encoding, physical_type: PhysicalType) -> bool {
+    match encoding {
+        Encoding::PLAIN => true,
+        Encoding::RLE => physical_type == PhysicalType::BOOLEAN,
+        Encoding::DELTA_BINARY_PACKED => {
+            matches!(physical_type, PhysicalType::INT32 | PhysicalType::INT64)
+        }
+        Encoding::DELTA_LENGTH_BYTE_ARRAY => physical_type == PhysicalType::BYTE_ARRAY,
+        Encoding::DELTA_BYTE_ARRAY => matches!(
+            physical_type,
+            PhysicalType::BYTE_ARRAY | PhysicalType::FIXED_LEN_BYTE_ARRAY
+        ),
+        Encoding::BYTE_STREAM_SPLIT => matches!(
+            physical_type,
+            PhysicalType::FLOAT
+                | PhysicalType::DOUBLE
+                | PhysicalType::INT32
+                | PhysicalType::INT64
+                | PhysicalType::FIXED_LEN_BYTE_ARRAY
+        ),
+        _ => false,
+    }
+}
+
+/// Parquet writer settings
+pub struct WriterSettings {
+    /// Default compression codec
+    pub compression: Compression,
+    /// Per-column encodings
+    pub column_encodings: Vec<(String, Encoding)>,
+    /// Columns with dictionary encoding disabled
+    pub no_dictionary: Vec<String>,
+    /// Per-column compression codecs
+    pub column_compressions: Vec<(String, Compression)>,
+}
+
+impl WriterSettings {
+    /// Builds parquet writer properties for the schema.
+    /// Returns an error if a column is not present in the schema.
+    pub fn build(&self, schema: &Schema) -> Result<WriterProperties> {
+        let column_path = |column: &str| -> Result<ColumnPath> {
+            if schema.field_with_name(column).is_err() {
+                return Err(anyhow!("Column `{column}' is not found in the schema"));
+            }
+            Ok(ColumnPath::from(column))
+        };
+
+        let parquet_schema = ArrowSchemaConverter::new().convert(schema)?;
+
+        let mut builder = WriterProperties::builder().set_compression(self.compression);
+        for (column, encoding) in &self.column_encodings {
+            let path = column_path(column)?;
+            let physical_type = parquet_schema
+                .columns()
+                .iter()
+                .find(|c| c.path() == &path)
+                .map(|c| c.physical_type())
+                .ok_or_else(|| anyhow!("Column `{column}' is not found in the schema"))?;
+            if !is_encoding_supported(*encoding, physical_type) {
+                return Err(anyhow!(
+                    "Encoding {encoding} is not supported for column `{column}' of type {physical_type}"
+                ));
+            }
+            // Dictionary encoding takes precedence over the column encoding, so we disable it
+            builder = builder
+                .set_column_dictionary_enabled(path.clone(), false)
+                .set_column_encoding(path, *encoding);
+        }
+        for column in &self.no_dictionary {
+            builder = builder.set_column_dictionary_enabled(column_path(column)?, false);
+        }
+        for (column, compression) in &self.column_compressions {
+            builder = builder.set_column_compression(column_path(column)?, *compression);
+        }
+        Ok(builder.build())
+    }
+}
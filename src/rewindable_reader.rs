@@ -1,52 +1,413 @@
 use std::{
     fs::File,
-    io::{Read, Seek},
+    io::{BufRead, BufReader, Cursor, Error, Read, Seek, SeekFrom, Write},
     path::Path,
 };
 
-use flate2::read::MultiGzDecoder;
+use bzip2::bufread::MultiBzDecoder;
+use clap::ValueEnum;
+use flate2::bufread::MultiGzDecoder;
+use memmap2::{Mmap, MmapOptions};
+use xz2::bufread::XzDecoder;
 
-/// Rewindable reader which can be used for both compressed and compressed files
-pub enum RewindableReader {
+use crate::{
+    archive::{ZipMember, split_member},
+    encoding::{TextSettings, Utf8Reader, ValidRows},
+    sample::sample_file,
+    stdio::{spool_tmp_file, stdin_file},
+    tempfile::TempFile,
+};
+
+/// Compression of input files
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum InputCompression {
     /// Uncompressed
-    Plain(File),
-    /// Compressed
-    Compressed(MultiGzDecoder<File>),
+    #[value(name = "none")]
+    Plain,
+    /// Gzip, usually .gz
+    Gzip,
+    /// Zstandard, usually .zst
+    Zstd,
+    /// Bzip2, usually .bz2
+    Bzip2,
+    /// Xz, usually .xz
+    Xz,
+    /// Lz4 frame format, usually .lz4
+    Lz4,
+    /// Snappy frame format, usually .sz
+    Snappy,
 }
 
-impl RewindableReader {
-    /// Opens plain or gzipped file and returns a reader
-    pub fn open(filename: &Path) -> std::io::Result<RewindableReader> {
-        let basename = filename.to_str().unwrap();
-        let file = File::open(filename)?;
-        if basename.ends_with(".gz") {
-            Ok(RewindableReader::Compressed(MultiGzDecoder::new(file)))
+impl InputCompression {
+    /// Detects compression by the magic bytes at the beginning of a file.
+    /// The bytes are left in the buffer, so non-seekable files can be detected as well.
+    fn detect(file: &mut BufReader<InputFile>) -> std::io::Result<Self> {
+        Ok(match file.fill_buf()? {
+            [0x1f, 0x8b, ..] => InputCompression::Gzip,
+            [0x28, 0xb5, 0x2f, 0xfd, ..] => InputCompression::Zstd,
+            [b'B', b'Z', b'h', b'1'..=b'9', ..] => InputCompression::Bzip2,
+            [0xfd, b'7', b'z', b'X', b'Z', 0x00, ..] => InputCompression::Xz,
+            [0x04, 0x22, 0x4d, 0x18, ..] => InputCompression::Lz4,
+            [0xff, 0x06, 0x00, 0x00, name @ ..] if name.starts_with(b"sNaPpY") => {
+                InputCompression::Snappy
+            }
+            _ => InputCompression::Plain,
+        })
+    }
+}
+
+/// Reader which can read data from the beginning again
+pub trait Rewind: Read + Sized {
+    /// Reads data from the beginning. Fails for non-seekable files.
+    fn rewind(self) -> std::io::Result<Self>;
+
+    /// Returns data of a mapped file which is passed as is. Returns `None` if data is read.
+    fn mapped(&self) -> Option<&[u8]>;
+
+    /// Reads a sample of a regular file which is passed as is, see [`sample_file`].
+    /// Returns `None` if data is read.
+    fn sample(&mut self, terminator: u8) -> Option<std::io::Result<Vec<u8>>>;
+}
+
+/// Maximum size of data kept in memory to replay it after rewinding
+pub const MAX_REPLAY_SIZE: usize = 64 << 20;
+
+/// Returns `true` if the file is a regular file rather than a pipe or a device
+fn is_regular(file: &File) -> bool {
+    file.metadata().is_ok_and(|metadata| metadata.is_file())
+}
+
+/// File read from its position at opening, like the standard input redirected from a file
+/// which is partly read. Positions are relative to it.
+struct InputFile {
+    file: File,
+    start: u64,
+}
+
+impl InputFile {
+    fn new(mut file: File) -> std::io::Result<Self> {
+        // Pipes have no position
+        let start = if is_regular(&file) {
+            file.stream_position()?
         } else {
-            Ok(RewindableReader::Plain(file))
+            0
+        };
+        Ok(Self { file, start })
+    }
+
+    fn is_regular(&self) -> bool {
+        is_regular(&self.file)
+    }
+}
+
+impl Read for InputFile {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.file.read(buf)
+    }
+}
+
+impl Seek for InputFile {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        let pos = match pos {
+            SeekFrom::Start(offset) => SeekFrom::Start(self.start + offset),
+            pos => pos,
+        };
+        Ok(self.file.seek(pos)?.saturating_sub(self.start))
+    }
+}
+
+/// Maps a regular file into memory. Returns `None` if the file can't be mapped.
+fn map_file(file: &InputFile) -> Option<Mmap> {
+    if !file.is_regular() {
+        return None;
+    }
+    // Like with any mapped file, changing the file while it's converted is undefined behavior
+    unsafe { MmapOptions::new().offset(file.start).map(&file.file) }.ok()
+}
+
+/// Decompressed data of a plain or compressed file, or a zip archive member
+enum Source {
+    /// Uncompressed file mapped into memory
+    Mapped(Cursor<Mmap>),
+    /// Uncompressed file which can't be mapped
+    Plain(BufReader<InputFile>),
+    /// Gzip compressed
+    Gzip(MultiGzDecoder<BufReader<InputFile>>),
+    /// Zstandard compressed
+    Zstd(zstd::Decoder<'static, BufReader<InputFile>>),
+    /// Bzip2 compressed
+    Bzip2(MultiBzDecoder<BufReader<InputFile>>),
+    /// Xz compressed
+    Xz(XzDecoder<BufReader<InputFile>>),
+    /// Lz4 frame compressed
+    Lz4(lz4_flex::frame::FrameDecoder<BufReader<InputFile>>),
+    /// Snappy frame compressed
+    Snappy(snap::read::FrameDecoder<BufReader<InputFile>>),
+    /// Member of a zip archive
+    Zip(ZipMember),
+}
+
+impl Source {
+    /// Opens a plain or compressed file. Compression is detected unless it's given.
+    fn open(file: File, compression: Option<InputCompression>) -> std::io::Result<Self> {
+        let mut file = BufReader::new(InputFile::new(file)?);
+        let compression = match compression {
+            Some(compression) => compression,
+            None => InputCompression::detect(&mut file)?,
+        };
+        match map_file(file.get_ref()) {
+            Some(map) if compression == InputCompression::Plain => {
+                Ok(Source::Mapped(Cursor::new(map)))
+            }
+            _ => Source::new(file, compression),
         }
     }
 
-    /// Rewinds a reader
-    pub fn rewind(self) -> std::io::Result<Self> {
+    /// Returns `true` if the source can be read again, unlike pipes
+    fn seekable(&self) -> bool {
         match self {
-            RewindableReader::Plain(mut file) => {
-                file.rewind()?;
-                Ok(RewindableReader::Plain(file))
+            Source::Mapped(_) | Source::Zip(_) => true,
+            Source::Plain(file) => file.get_ref().is_regular(),
+            Source::Gzip(decoder) => decoder.get_ref().get_ref().is_regular(),
+            Source::Zstd(decoder) => decoder.get_ref().get_ref().is_regular(),
+            Source::Bzip2(decoder) => decoder.get_ref().get_ref().is_regular(),
+            Source::Xz(decoder) => decoder.get_ref().get_ref().is_regular(),
+            Source::Lz4(decoder) => decoder.get_ref().get_ref().is_regular(),
+            Source::Snappy(decoder) => decoder.get_ref().get_ref().is_regular(),
+        }
+    }
+
+    fn new(file: BufReader<InputFile>, compression: InputCompression) -> std::io::Result<Self> {
+        Ok(match compression {
+            InputCompression::Plain => Source::Plain(file),
+            InputCompression::Gzip => Source::Gzip(MultiGzDecoder::new(file)),
+            InputCompression::Zstd => Source::Zstd(zstd::Decoder::with_buffer(file)?),
+            InputCompression::Bzip2 => Source::Bzip2(MultiBzDecoder::new(file)),
+            InputCompression::Xz => Source::Xz(XzDecoder::new_multi_decoder(file)),
+            InputCompression::Lz4 => Source::Lz4(lz4_flex::frame::FrameDecoder::new(file)),
+            InputCompression::Snappy => Source::Snappy(snap::read::FrameDecoder::new(file)),
+        })
+    }
+}
+
+impl Rewind for Source {
+    fn rewind(self) -> std::io::Result<Self> {
+        let (mut file, compression) = match self {
+            Source::Mapped(mut data) => {
+                data.set_position(0);
+                return Ok(Source::Mapped(data));
             }
-            RewindableReader::Compressed(multi_gz_decoder) => {
-                let mut file = multi_gz_decoder.into_inner();
-                file.rewind()?;
-                Ok(RewindableReader::Compressed(MultiGzDecoder::new(file)))
+            Source::Plain(file) => (file, InputCompression::Plain),
+            Source::Gzip(decoder) => (decoder.into_inner(), InputCompression::Gzip),
+            Source::Zstd(decoder) => (decoder.finish(), InputCompression::Zstd),
+            Source::Bzip2(decoder) => (decoder.into_inner(), InputCompression::Bzip2),
+            Source::Xz(decoder) => (decoder.into_inner(), InputCompression::Xz),
+            Source::Lz4(decoder) => (decoder.into_inner(), InputCompression::Lz4),
+            Source::Snappy(decoder) => (decoder.into_inner(), InputCompression::Snappy),
+            Source::Zip(member) => return Ok(Source::Zip(member.rewind()?)),
+        };
+        if !file.get_ref().is_regular() {
+            return Err(Error::other(
+                "The input is a pipe which can't be read again, convert a regular file instead",
+            ));
+        }
+        file.rewind()?;
+        Source::new(file, compression)
+    }
+
+    fn mapped(&self) -> Option<&[u8]> {
+        match self {
+            Source::Mapped(data) => Some(data.get_ref()),
+            _ => None,
+        }
+    }
+
+    fn sample(&mut self, terminator: u8) -> Option<std::io::Result<Vec<u8>>> {
+        match self {
+            Source::Mapped(data) => Some(sample_file(data, terminator)),
+            Source::Plain(file) if file.get_ref().is_regular() => {
+                Some(sample_file(file, terminator))
             }
+            _ => None,
         }
     }
 }
 
-impl Read for RewindableReader {
+impl Read for Source {
     fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
         match self {
-            RewindableReader::Plain(file) => file.read(buf),
-            RewindableReader::Compressed(multi_gz_decoder) => multi_gz_decoder.read(buf),
+            Source::Mapped(data) => data.read(buf),
+            Source::Plain(file) => file.read(buf),
+            Source::Gzip(decoder) => decoder.read(buf),
+            Source::Zstd(decoder) => decoder.read(buf),
+            Source::Bzip2(decoder) => decoder.read(buf),
+            Source::Xz(decoder) => decoder.read(buf),
+            Source::Lz4(decoder) => decoder.read(buf),
+            Source::Snappy(decoder) => decoder.read(buf),
+            Source::Zip(member) => member.read(buf),
+        }
+    }
+}
+
+/// Rewindable reader which can be used for both compressed and compressed files.
+/// Data read from the beginning is kept in memory and replayed after rewinding, so the file
+/// is read only once, which makes pipes readable too. The source itself is rewound only if
+/// more than [`MAX_REPLAY_SIZE`] bytes are read before rewinding. Pipes can't be rewound,
+/// so their data beyond it is kept in a temporary file. Uncompressed regular files
+/// are mapped into memory and rewound without keeping data.
+/// Data is converted to UTF-8 before it's kept, see [`Utf8Reader`].
+pub struct RewindableReader {
+    text: ValidRows<Utf8Reader<Source>>,
+    /// Whether the source can be rewound
+    seekable: bool,
+    /// Data read from the beginning of the source
+    buffer: Vec<u8>,
+    /// Position of the next replayed byte in `buffer`
+    position: usize,
+    /// Data of a pipe read after `buffer` is full
+    spool: Option<TempFile>,
+    /// Size of data in `spool`
+    spooled: u64,
+    /// Position of the next replayed byte in `spool`
+    spool_position: u64,
+    /// Whether data read from the source is appended to `buffer`
+    recording: bool,
+    /// Whether `buffer` contains all data read from the source
+    complete: bool,
+}
+
+impl RewindableReader {
+    /// Opens plain or compressed file, or a zip archive member, and returns a reader.
+    /// Compression is detected by the magic bytes unless it's given.
+    pub fn open(
+        filename: &Path,
+        compression: Option<InputCompression>,
+        settings: TextSettings,
+    ) -> std::io::Result<RewindableReader> {
+        let source = match split_member(filename) {
+            Some((archive, name)) => Source::Zip(ZipMember::open(archive, &name)?),
+            None => Source::open(File::open(filename)?, compression)?,
+        };
+        RewindableReader::new(source, settings)
+    }
+
+    /// Opens the standard input like [`RewindableReader::open`]. Pipes are read only once.
+    pub fn stdin(
+        compression: Option<InputCompression>,
+        settings: TextSettings,
+    ) -> std::io::Result<RewindableReader> {
+        let source = Source::open(stdin_file()?, compression)?;
+        RewindableReader::new(source, settings)
+    }
+
+    fn new(source: Source, settings: TextSettings) -> std::io::Result<RewindableReader> {
+        // Mapped data is cheap to read again
+        let recording = !matches!(source, Source::Mapped(_));
+        let seekable = source.seekable();
+        let text = Utf8Reader::new(source, settings.clone())?;
+        Ok(RewindableReader {
+            text: ValidRows::new(text, settings),
+            seekable,
+            recording,
+            buffer: vec![],
+            position: 0,
+            spool: None,
+            spooled: 0,
+            spool_position: 0,
+            complete: true,
+        })
+    }
+
+    /// Returns `true` if the source can be read again without keeping its data
+    pub fn seekable(&self) -> bool {
+        self.seekable
+    }
+
+    /// Returns data of a mapped file which is not read yet
+    pub fn mapped(&self) -> Option<&[u8]> {
+        self.text.mapped()
+    }
+
+    /// Reads a sample of a regular uncompressed file which is not read yet, see [`sample_file`]
+    pub fn sample(&mut self, terminator: u8) -> Option<std::io::Result<Vec<u8>>> {
+        if !self.buffer.is_empty() || !self.complete {
+            return None;
+        }
+        self.text.sample(terminator)
+    }
+
+    /// Stops keeping data read from the source in memory. Used before the last pass
+    /// over the data.
+    pub fn stop_recording(&mut self) {
+        self.recording = false;
+    }
+
+    /// Rewinds a reader
+    pub fn rewind(mut self) -> std::io::Result<Self> {
+        if !self.complete {
+            self.text = self.text.rewind()?;
+            self.buffer = vec![];
+            self.spool = None;
+            self.spooled = 0;
+            self.complete = true;
+        }
+        self.position = 0;
+        if let Some(spool) = &mut self.spool {
+            spool.rewind()?;
+        }
+        self.spool_position = 0;
+        Ok(self)
+    }
+
+    /// Keeps data read from the source for replaying it
+    fn record(&mut self, data: &[u8]) -> std::io::Result<bool> {
+        if self.spool.is_none() && self.buffer.len() + data.len() <= MAX_REPLAY_SIZE {
+            self.buffer.extend_from_slice(data);
+            self.position = self.buffer.len();
+            return Ok(true);
+        }
+        if self.seekable {
+            return Ok(false);
+        }
+        let spool = match &mut self.spool {
+            Some(spool) => spool,
+            None => self.spool.insert(spool_tmp_file()?),
+        };
+        spool.write_all(data)?;
+        self.spooled += data.len() as u64;
+        self.spool_position = self.spooled;
+        Ok(true)
+    }
+}
+
+impl Read for RewindableReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.position < self.buffer.len() {
+            let replayed = (&self.buffer[self.position..]).read(buf)?;
+            self.position += replayed;
+            return Ok(replayed);
+        }
+        if let Some(spool) = &mut self.spool
+            && self.spool_position < self.spooled
+        {
+            let length = buf.len().min((self.spooled - self.spool_position) as usize);
+            let replayed = spool.read(&mut buf[..length])?;
+            self.spool_position += replayed as u64;
+            return Ok(replayed);
+        }
+        let read = self.text.read(buf)?;
+        if read == 0 {
+            return Ok(0);
+        }
+        if self.complete && !(self.recording && self.record(&buf[..read])?) {
+            // The source has to be rewound to read the data again
+            self.complete = false;
+            self.buffer = vec![];
+            self.position = 0;
+            self.spool = None;
+            self.spooled = 0;
+            self.spool_position = 0;
         }
+        Ok(read)
     }
 }
@@ -0,0 +1,123 @@
+use std::io::{Read, Seek, SeekFrom};
+
+/// Number of blocks read across the file in the sampled inference mode
+pub const SAMPLE_BLOCKS: u64 = 16;
+
+/// Size of a block read in the sampled inference mode
+pub const SAMPLE_BLOCK_SIZE: u64 = 1 << 20;
+
+/// Reads blocks spread evenly across the file. The first block starts at the beginning of
+/// the file and contains the header, the last one ends at the end of the file. Other blocks
+/// start at the line following their offsets. Blocks overlapping the previous ones continue them.
+/// Every block is cut after the last complete line.
+pub fn sample_file(file: &mut (impl Read + Seek), terminator: u8) -> std::io::Result<Vec<u8>> {
+    let position = file.stream_position()?;
+    let size = file.seek(SeekFrom::End(0))?;
+    let mut sample = Vec::new();
+    let mut covered = 0;
+    // Whether `covered` is at the beginning of a line
+    let mut aligned = true;
+    let span = size.saturating_sub(SAMPLE_BLOCK_SIZE);
+    for i in 0..SAMPLE_BLOCKS {
+        let mut offset = span * i / (SAMPLE_BLOCKS - 1);
+        // Blocks overlap for small files, so they continue the previous ones
+        let continued = i > 0 && offset <= covered;
+        if continued {
+            if covered >= size {
+                break;
+            }
+            offset = covered;
+        }
+        file.seek(SeekFrom::Start(offset))?;
+        let mut block = Vec::with_capacity(SAMPLE_BLOCK_SIZE as usize);
+        file.by_ref()
+            .take(SAMPLE_BLOCK_SIZE)
+            .read_to_end(&mut block)?;
+        let start = if i == 0 || continued && aligned {
+            Some(0)
+        } else {
+            block.iter().position(|&b| b == terminator).map(|p| p + 1)
+        };
+        let end = if offset + (block.len() as u64) < size {
+            block.iter().rposition(|&b| b == terminator).map(|p| p + 1)
+        } else {
+            Some(block.len())
+        };
+        match (start, end) {
+            (Some(start), Some(end)) if start <= end => {
+                sample.extend_from_slice(&block[start..end]);
+                if sample.last().is_some_and(|&b| b != terminator) {
+                    sample.push(terminator);
+                }
+                covered = offset + end as u64;
+                aligned = true;
+            }
+            _ => {
+                covered = offset + block.len() as u64;
+                aligned = false;
+            }
+        }
+    }
+    file.seek(SeekFrom::Start(position))?;
+    Ok(sample)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    fn lines(count: usize) -> Vec<u8> {
+        let mut data = b"id,value\n".to_vec();
+        for i in 0..count {
+            data.extend_from_slice(format!("{i},{}\n", i * 7).as_bytes());
+        }
+        data
+    }
+
+    #[test]
+    fn small_file_is_read_whole() {
+        let data = lines(100);
+        let mut file = Cursor::new(data.clone());
+        assert_eq!(sample_file(&mut file, b'\n').unwrap(), data);
+    }
+
+    #[test]
+    fn overlapping_blocks_cover_the_file() {
+        // About 2.5 blocks, so every block overlaps the previous one
+        let mut data = lines(250_000);
+        data.extend_from_slice(b"3.5,y");
+        assert!(data.len() as u64 > 2 * SAMPLE_BLOCK_SIZE);
+        let mut file = Cursor::new(data.clone());
+        file.set_position(3);
+        let sample = sample_file(&mut file, b'\n').unwrap();
+        assert_eq!(file.position(), 3);
+        data.push(b'\n');
+        assert_eq!(sample, data);
+    }
+
+    #[test]
+    fn large_file_is_sampled_to_the_end() {
+        let data = lines(3_000_000);
+        let size = data.len() as u64;
+        assert!(size > SAMPLE_BLOCKS * SAMPLE_BLOCK_SIZE);
+        let sample = sample_file(&mut Cursor::new(data.clone()), b'\n').unwrap();
+        assert!(sample.starts_with(b"id,value\n0,0\n"));
+        assert!(sample.ends_with(format!("{},{}\n", 2_999_999, 2_999_999 * 7).as_bytes()));
+        assert!((sample.len() as u64) < SAMPLE_BLOCKS * SAMPLE_BLOCK_SIZE);
+        // Every sampled line is a complete line of the file
+        for line in sample
+            .split(|&b| b == b'\n')
+            .filter(|line| !line.is_empty())
+            .skip(1)
+        {
+            let line = std::str::from_utf8(line).unwrap();
+            let (id, value) = line.split_once(',').unwrap();
+            assert_eq!(
+                value.parse::<u64>().unwrap(),
+                id.parse::<u64>().unwrap() * 7
+            );
+        }
+    }
+}
@@ -0,0 +1,275 @@
+use std::{fs::File, io::BufReader, path::Path, str::FromStr};
+
+use anyhow::{Result, anyhow};
+use arrow_schema::{
+    ArrowError, DECIMAL128_MAX_PRECISION, DECIMAL256_MAX_PRECISION, DataType, Field, Fields,
+    Schema, TimeUnit,
+};
+
+use crate::{properties::split_column_spec, timestamp::parse_timezone};
+
+/// Loads a schema from a JSON file produced by `--print-schema`
+pub fn load_schema(filename: &Path) -> Result<Schema> {
+    let file = File::open(filename)
+        .map_err(|err| anyhow!("Can't open schema file {}: {err}", filename.display()))?;
+    serde_json::from_reader(BufReader::new(file))
+        .map_err(|err| anyhow!("Can't parse schema file {}: {err}", filename.display()))
+}
+
+/// Checks that the csv header matches the schema. Column names are checked only
+/// if `check_names` is `true`.
+pub fn check_header(header: &Schema, schema: &Schema, check_names: bool) -> Result<()> {
+    if header.fields().len() != schema.fields().len() {
+        return Err(anyhow!(
+            "Schema has {} columns, but {} columns were found",
+            schema.fields().len(),
+            header.fields().len()
+        ));
+    }
+    for (i, (found, expected)) in header.fields().iter().zip(schema.fields()).enumerate() {
+        if check_names && found.name() != expected.name() {
+            eprintln!(
+                "Warning: column {} is named `{}' in the header, but `{}' in the schema",
+                i + 1,
+                found.name(),
+                expected.name()
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Error caused by a value of a specific column
+#[derive(Debug)]
+pub struct ColumnError {
+    /// Column index
+    pub column: usize,
+    /// Error message
+    pub message: String,
+}
+
+impl std::fmt::Display for ColumnError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for ColumnError {}
+
+/// Adds the column name to arrow-csv parse errors which refer to columns by index only
+pub fn describe_parse_error(err: ArrowError, schema: &Schema) -> anyhow::Error {
+    let column = match &err {
+        ArrowError::ParseError(message) => message
+            .split_once("column ")
+            .and_then(|(_, rest)| rest.split(|c: char| !c.is_ascii_digit()).next())
+            .and_then(|index| index.parse::<usize>().ok())
+            .filter(|&index| index < schema.fields().len()),
+        _ => None,
+    };
+    match column {
+        Some(column) => {
+            let field = schema.field(column);
+            ColumnError {
+                column,
+                message: format!(
+                    "{err} (column `{}' of type {})",
+                    field.name(),
+                    field.data_type()
+                ),
+            }
+            .into()
+        }
+        None => err.into(),
+    }
+}
+
+/// Returns a data type which can hold values the data type can't: integers are widened to
+/// Float64 and other types to Utf8. Returns `None` for strings.
+pub fn widen(data_type: &DataType) -> Option<DataType> {
+    match data_type {
+        DataType::Utf8 | DataType::LargeUtf8 => None,
+        data_type if data_type.is_integer() => Some(DataType::Float64),
+        _ => Some(DataType::Utf8),
+    }
+}
+
+/// Returns a data type which can hold values of both data types
+fn merge_data_types(a: &DataType, b: &DataType) -> DataType {
+    use DataType::*;
+    match (a, b) {
+        (a, b) if a == b => a.clone(),
+        (Null, other) | (other, Null) => other.clone(),
+        (a, b) if a.is_integer() && b.is_integer() => {
+            if a.is_signed_integer() == b.is_signed_integer() {
+                if a.primitive_width() >= b.primitive_width() {
+                    a.clone()
+                } else {
+                    b.clone()
+                }
+            } else {
+                Int64
+            }
+        }
+        (Float16 | Float32 | Float64, Float16 | Float32 | Float64) => Float64,
+        // Float32 loses precision of integers above 2^24, Float64 only above 2^53
+        (float, int) | (int, float) if float.is_floating() && int.is_integer() => Float64,
+        (Decimal128(p1, s1) | Decimal256(p1, s1), Decimal128(p2, s2) | Decimal256(p2, s2)) => {
+            let scale = *s1.max(s2);
+            let integer = (*p1 as i16 - *s1 as i16).max(*p2 as i16 - *s2 as i16);
+            let precision = (integer + scale as i16).max(1) as u8;
+            if precision <= DECIMAL128_MAX_PRECISION {
+                Decimal128(precision, scale)
+            } else if precision <= DECIMAL256_MAX_PRECISION {
+                Decimal256(precision, scale)
+            } else {
+                Utf8
+            }
+        }
+        (decimal @ (Decimal128(_, _) | Decimal256(_, _)), int)
+        | (int, decimal @ (Decimal128(_, _) | Decimal256(_, _)))
+            if int.is_integer() =>
+        {
+            decimal.clone()
+        }
+        (Timestamp(u1, tz1), Timestamp(u2, tz2)) if tz1 == tz2 => {
+            // Time units are ordered from seconds to nanoseconds
+            Timestamp(if *u1 as u8 >= *u2 as u8 { *u1 } else { *u2 }, tz1.clone())
+        }
+        (timestamp @ Timestamp(_, _), Date32 | Date64)
+        | (Date32 | Date64, timestamp @ Timestamp(_, _)) => timestamp.clone(),
+        (Date32, Date64) | (Date64, Date32) => Date64,
+        _ => Utf8,
+    }
+}
+
+/// Merges schemas of several files. Files must have the same columns,
+/// conflicting data types are widened.
+pub fn merge_schemas(schemas: &[Schema]) -> Result<Schema> {
+    let Some((first, others)) = schemas.split_first() else {
+        return Err(anyhow!("No schemas to merge"));
+    };
+    let mut fields: Vec<Field> = first.fields().iter().map(|f| f.as_ref().clone()).collect();
+    for schema in others {
+        let names = |schema: &Schema| -> Vec<String> {
+            schema.fields().iter().map(|f| f.name().clone()).collect()
+        };
+        if schema.fields().len() != fields.len()
+            || schema
+                .fields()
+                .iter()
+                .zip(&fields)
+                .any(|(a, b)| a.name() != b.name())
+        {
+            return Err(anyhow!(
+                "Can't unify schemas: files have different columns: {:?} and {:?}",
+                names(first),
+                names(schema),
+            ));
+        }
+        for (field, other) in fields.iter_mut().zip(schema.fields()) {
+            let data_type = merge_data_types(field.data_type(), other.data_type());
+            *field = field.clone().with_data_type(data_type).with_nullable(true);
+        }
+    }
+    Ok(Schema::new(Fields::from(fields)))
+}
+
+/// Parses a time unit: `s`, `ms`, `us` or `ns`
+pub fn parse_time_unit(s: &str) -> Result<TimeUnit> {
+    match s.trim().to_ascii_lowercase().as_str() {
+        "s" | "second" => Ok(TimeUnit::Second),
+        "ms" | "millisecond" => Ok(TimeUnit::Millisecond),
+        "us" | "microsecond" => Ok(TimeUnit::Microsecond),
+        "ns" | "nanosecond" => Ok(TimeUnit::Nanosecond),
+        _ => Err(anyhow!(
+            "Invalid time unit `{s}'. Expected one of: s, ms, us, ns"
+        )),
+    }
+}
+
+/// Parses decimal precision and scale, e.g. `18,4`
+fn parse_decimal(args: &str, max_precision: u8) -> Result<(u8, i8)> {
+    let (precision, scale) = args.split_once(',').unwrap_or((args, "0"));
+    let precision = precision
+        .trim()
+        .parse::<u8>()
+        .map_err(|_| anyhow!("Invalid decimal precision `{precision}'"))?;
+    let scale = scale
+        .trim()
+        .parse::<i8>()
+        .map_err(|_| anyhow!("Invalid decimal scale `{scale}'"))?;
+    if precision == 0 || precision > max_precision {
+        return Err(anyhow!(
+            "Decimal precision must be between 1 and {max_precision}"
+        ));
+    }
+    if scale > precision as i8 {
+        return Err(anyhow!(
+            "Decimal scale {scale} is greater than precision {precision}"
+        ));
+    }
+    Ok((precision, scale))
+}
+
+/// Parses a data type, e.g. `utf8`, `int16`, `decimal(18,4)` or `timestamp[ms,UTC]`.
+/// Arrow data type names like `Timestamp(Millisecond, None)` are accepted as well.
+pub fn parse_data_type(s: &str) -> Result<DataType> {
+    let data_type = parse_type_name(s.trim())?;
+    if let DataType::Timestamp(_, Some(tz)) = &data_type {
+        parse_timezone(tz)?;
+    }
+    Ok(data_type)
+}
+
+fn parse_type_name(s: &str) -> Result<DataType> {
+    if let Ok(data_type) = DataType::from_str(s) {
+        return Ok(data_type);
+    }
+    let (name, args) = match s.split_once(['(', '[']) {
+        Some((name, args)) => match args.strip_suffix([')', ']']) {
+            Some(args) => (name.trim(), Some(args)),
+            None => return Err(anyhow!("Invalid data type `{s}'")),
+        },
+        None => (s, None),
+    };
+    let data_type = match (name.to_ascii_lowercase().as_str(), args) {
+        ("utf8" | "string" | "str", None) => DataType::Utf8,
+        ("large_utf8" | "large_string", None) => DataType::LargeUtf8,
+        ("binary", None) => DataType::Binary,
+        ("bool" | "boolean", None) => DataType::Boolean,
+        ("i8" | "int8", None) => DataType::Int8,
+        ("i16" | "int16", None) => DataType::Int16,
+        ("i32" | "int32", None) => DataType::Int32,
+        ("i64" | "int64", None) => DataType::Int64,
+        ("u8" | "uint8", None) => DataType::UInt8,
+        ("u16" | "uint16", None) => DataType::UInt16,
+        ("u32" | "uint32", None) => DataType::UInt32,
+        ("u64" | "uint64", None) => DataType::UInt64,
+        ("f16" | "float16", None) => DataType::Float16,
+        ("f32" | "float32" | "float", None) => DataType::Float32,
+        ("f64" | "float64" | "double", None) => DataType::Float64,
+        ("date" | "date32", None) => DataType::Date32,
+        ("date64", None) => DataType::Date64,
+        ("timestamp", None) => DataType::Timestamp(TimeUnit::Microsecond, None),
+        ("timestamp", Some(args)) => match args.split_once(',') {
+            Some((unit, tz)) => DataType::Timestamp(parse_time_unit(unit)?, Some(tz.trim().into())),
+            None => DataType::Timestamp(parse_time_unit(args)?, None),
+        },
+        ("decimal" | "decimal128", Some(args)) => {
+            let (precision, scale) = parse_decimal(args, DECIMAL128_MAX_PRECISION)?;
+            DataType::Decimal128(precision, scale)
+        }
+        ("decimal256", Some(args)) => {
+            let (precision, scale) = parse_decimal(args, DECIMAL256_MAX_PRECISION)?;
+            DataType::Decimal256(precision, scale)
+        }
+        _ => return Err(anyhow!("Unknown data type `{s}'")),
+    };
+    Ok(data_type)
+}
+
+/// Parses a `COLUMN=TYPE` specification, e.g. `amount=decimal(18,4)`
+pub fn parse_column_type(spec: &str) -> Result<(String, DataType)> {
+    let (column, data_type) = split_column_spec(spec, "TYPE")?;
+    Ok((column, parse_data_type(data_type)?))
+}
@@ -0,0 +1,64 @@
+#[cfg(unix)]
+use std::os::fd::AsFd;
+#[cfg(windows)]
+use std::os::windows::io::AsHandle;
+use std::{
+    env::temp_dir,
+    fs::File,
+    io::{Seek, stdin},
+    path::Path,
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::tempfile::TempFile;
+
+/// File name standing for the standard input or output
+pub const STDIO: &str = "-";
+
+/// Returns `true` if the file name stands for the standard input or output
+pub fn is_stdio(filename: &Path) -> bool {
+    filename == Path::new(STDIO)
+}
+
+/// Returns a temporary file in the system temporary directory
+fn tmp_file(name: &str) -> std::io::Result<TempFile> {
+    let path = temp_dir().join(format!(".tmp.csv2pq-{}-{name}", process::id()));
+    TempFile::create_new(path.into_os_string().into_string().unwrap())
+}
+
+/// Returns the standard input as a file, which is a pipe or a redirected file
+pub fn stdin_file() -> std::io::Result<File> {
+    #[cfg(unix)]
+    let handle = stdin().as_fd().try_clone_to_owned()?;
+    #[cfg(windows)]
+    let handle = stdin().as_handle().try_clone_to_owned()?;
+    Ok(File::from(handle))
+}
+
+/// Returns the size of the standard input redirected from a file which is left to read.
+/// The size of pipes is unknown and 0.
+pub fn stdin_size() -> u64 {
+    stdin_file()
+        .and_then(|mut file| {
+            Ok(file
+                .metadata()?
+                .len()
+                .saturating_sub(file.stream_position()?))
+        })
+        .unwrap_or(0)
+}
+
+/// Creates a temporary file keeping data of a pipe to read it again
+pub fn spool_tmp_file() -> std::io::Result<TempFile> {
+    static SPOOLED: AtomicUsize = AtomicUsize::new(0);
+    tmp_file(&format!(
+        "spool-{}.csv",
+        SPOOLED.fetch_add(1, Ordering::Relaxed)
+    ))
+}
+
+/// Creates a temporary file for the Parquet file written to the standard output
+pub fn stdout_tmp_file() -> std::io::Result<TempFile> {
+    tmp_file("stdout.parquet")
+}
@@ -0,0 +1,240 @@
+use std::{
+    path::Path,
+    process::ExitCode,
+    time::{Duration, Instant},
+};
+
+use arrow_schema::Schema;
+use serde_json::{Value, json};
+
+/// Exit code if all files failed
+pub const EXIT_FAILURE: u8 = 1;
+
+/// Exit code if some files failed. Clap exits with 2 on invalid arguments.
+pub const EXIT_PARTIAL_FAILURE: u8 = 3;
+
+/// Result of processing a csv file
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Status {
+    #[default]
+    Converted,
+    Skipped,
+    Failed,
+}
+
+impl Status {
+    fn as_str(self) -> &'static str {
+        match self {
+            Status::Converted => "converted",
+            Status::Skipped => "skipped",
+            Status::Failed => "failed",
+        }
+    }
+}
+
+/// Report of a processed csv file
+#[derive(Default)]
+pub struct FileReport {
+    pub filename: String,
+    pub status: Status,
+    /// Reason of skipping or failure
+    pub message: Option<String>,
+    /// Parquet file
+    pub output: Option<String>,
+    /// Schema before adaptive widening
+    pub inferred_schema: Option<Schema>,
+    /// Schema of the Parquet file
+    pub schema: Option<Schema>,
+    pub rows: u64,
+    /// Number of row groups. Like `bytes_out`, it is `None` if several csv files are written
+    /// into the Parquet file.
+    pub row_groups: Option<usize>,
+    pub bytes_in: u64,
+    /// Size of the Parquet file
+    pub bytes_out: Option<u64>,
+    pub elapsed: Duration,
+}
+
+impl FileReport {
+    /// Creates a report of a converted file
+    pub fn converted(filename: &Path, output: &Path, rows: u64, bytes_in: u64) -> Self {
+        Self {
+            filename: filename.to_str().unwrap().to_string(),
+            status: Status::Converted,
+            output: Some(output.to_str().unwrap().to_string()),
+            rows,
+            bytes_in,
+            ..Default::default()
+        }
+    }
+
+    /// Creates a report of a skipped file
+    pub fn skipped(filename: &Path, message: String) -> Self {
+        Self {
+            filename: filename.to_str().unwrap().to_string(),
+            status: Status::Skipped,
+            message: Some(message),
+            ..Default::default()
+        }
+    }
+
+    /// Creates a report of a failed file
+    pub fn failed(filename: &Path, err: &anyhow::Error) -> Self {
+        Self {
+            filename: filename.to_str().unwrap().to_string(),
+            status: Status::Failed,
+            message: Some(format!("{err:#}")),
+            ..Default::default()
+        }
+    }
+
+    /// Returns the report as JSON
+    fn to_json(&self) -> Value {
+        json!({
+            "filename": self.filename,
+            "status": self.status.as_str(),
+            "error": self.message.as_ref().filter(|_| self.status == Status::Failed),
+            "skip_reason": self.message.as_ref().filter(|_| self.status == Status::Skipped),
+            "output": self.output,
+            "inferred_schema": self.inferred_schema,
+            "schema": self.schema,
+            "rows": self.rows,
+            "row_groups": self.row_groups,
+            "bytes_in": self.bytes_in,
+            "bytes_out": self.bytes_out,
+            "compression_ratio": compression_ratio(self.bytes_in, self.bytes_out),
+            "elapsed_seconds": self.elapsed.as_secs_f64(),
+        })
+    }
+}
+
+/// Returns the ratio of csv and Parquet sizes
+fn compression_ratio(bytes_in: u64, bytes_out: Option<u64>) -> Option<f64> {
+    bytes_out
+        .filter(|&bytes_out| bytes_out > 0)
+        .map(|bytes_out| bytes_in as f64 / bytes_out as f64)
+}
+
+/// Returns the size of a file or 0 if it can't be read
+pub fn file_size(filename: &Path) -> u64 {
+    filename.metadata().map_or(0, |metadata| metadata.len())
+}
+
+/// Summary of a run
+pub struct Summary {
+    pub files: Vec<FileReport>,
+    /// Error which stopped the run before or between files
+    pub error: Option<String>,
+    /// Total size of written Parquet files
+    pub bytes_out: u64,
+    /// Start of the run
+    started: Instant,
+    /// Start of processing the current file. Every file is processed with its own summary.
+    file_started: Instant,
+}
+
+impl Default for Summary {
+    fn default() -> Self {
+        let now = Instant::now();
+        Self {
+            files: vec![],
+            error: None,
+            bytes_out: 0,
+            started: now,
+            file_started: now,
+        }
+    }
+}
+
+impl Summary {
+    /// Adds a file report
+    pub fn add(&mut self, mut report: FileReport) {
+        self.bytes_out += report.bytes_out.unwrap_or(0);
+        report.elapsed = self.file_started.elapsed();
+        self.files.push(report);
+    }
+
+    /// Records an error which stopped the run
+    pub fn fail(&mut self, err: &anyhow::Error) {
+        self.error = Some(format!("{err:#}"));
+    }
+
+    /// Appends reports of another summary
+    pub fn extend(&mut self, other: Summary) {
+        self.bytes_out += other.bytes_out;
+        self.files.extend(other.files);
+    }
+
+    /// Returns the number of files with the status
+    pub fn count(&self, status: Status) -> usize {
+        self.files.iter().filter(|f| f.status == status).count()
+    }
+
+    /// Returns the total number of converted rows
+    pub fn rows(&self) -> u64 {
+        self.files.iter().map(|f| f.rows).sum()
+    }
+
+    /// Returns the total size of converted csv files
+    pub fn bytes_in(&self) -> u64 {
+        self.files.iter().map(|f| f.bytes_in).sum()
+    }
+
+    /// Prints the list of processed files and totals to stderr
+    pub fn print(&self) {
+        for file in &self.files {
+            let status = file.status.as_str();
+            match (&file.message, file.bytes_out) {
+                (Some(message), _) => eprintln!("{status:>9} {}: {message}", file.filename),
+                (None, Some(bytes_out)) => eprintln!(
+                    "{status:>9} {}: {} rows, {} bytes in, {bytes_out} bytes out",
+                    file.filename, file.rows, file.bytes_in
+                ),
+                (None, None) => eprintln!(
+                    "{status:>9} {}: {} rows, {} bytes in",
+                    file.filename, file.rows, file.bytes_in
+                ),
+            }
+        }
+        eprintln!(
+            "{} converted, {} skipped, {} failed: {} rows, {} bytes in, {} bytes out",
+            self.count(Status::Converted),
+            self.count(Status::Skipped),
+            self.count(Status::Failed),
+            self.rows(),
+            self.bytes_in(),
+            self.bytes_out,
+        );
+    }
+
+    /// Returns the summary as JSON
+    pub fn to_json(&self) -> Value {
+        json!({
+            "files": self.files.iter().map(FileReport::to_json).collect::<Vec<_>>(),
+            "error": self.error,
+            "converted": self.count(Status::Converted),
+            "skipped": self.count(Status::Skipped),
+            "failed": self.count(Status::Failed),
+            "rows": self.rows(),
+            "bytes_in": self.bytes_in(),
+            "bytes_out": self.bytes_out,
+            "compression_ratio": compression_ratio(self.bytes_in(), Some(self.bytes_out)),
+            "elapsed_seconds": self.started.elapsed().as_secs_f64(),
+        })
+    }
+
+    /// Returns the exit code: success if no files failed, partial failure if some files
+    /// were converted or skipped and failure otherwise or if the run was stopped
+    pub fn exit_code(&self) -> ExitCode {
+        let failed = self.count(Status::Failed);
+        if self.error.is_some() {
+            ExitCode::from(EXIT_FAILURE)
+        } else if failed == 0 {
+            ExitCode::SUCCESS
+        } else if failed < self.files.len() {
+            ExitCode::from(EXIT_PARTIAL_FAILURE)
+        } else {
+            ExitCode::from(EXIT_FAILURE)
+        }
+    }
+}
@@ -1,6 +1,6 @@
 use std::{
     fs::{remove_file, rename, File},
-    io::Write,
+    io::{copy, Read, Seek, SeekFrom, Write},
     path::Path,
 };
 
@@ -17,6 +17,21 @@ impl TempFile {
         Ok(Self { tmp_filename, file })
     }
 
+    /// Discards written data
+    pub fn reset(&mut self) -> std::io::Result<()> {
+        self.file.set_len(0)?;
+        self.file.rewind()
+    }
+
+    /// Copies written data to the writer and removes the temporary file.
+    /// Returns the number of copied bytes.
+    pub fn copy_to(mut self, writer: &mut impl Write) -> std::io::Result<u64> {
+        self.file.rewind()?;
+        let size = copy(&mut self.file, writer)?;
+        writer.flush()?;
+        Ok(size)
+    }
+
     /// Flushes data and renames temporary file to a new one
     pub fn flush_and_rename(self, new_filename: impl AsRef<Path>) -> std::io::Result<()> {
         self.file.sync_all()?;
@@ -34,6 +49,18 @@ impl Write for TempFile {
     }
 }
 
+impl Read for TempFile {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.file.read(buf)
+    }
+}
+
+impl Seek for TempFile {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.file.seek(pos)
+    }
+}
+
 impl Drop for TempFile {
     fn drop(&mut self) {
         let _ = remove_file(&self.tmp_filename);
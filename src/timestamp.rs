@@ -0,0 +1,279 @@
+use std::{str::FromStr, sync::Arc};
+
+use anyhow::{Result, anyhow};
+use arrow_array::{
+    ArrayRef, Date32Array, Date64Array, StringArray, TimestampMicrosecondArray,
+    TimestampMillisecondArray, TimestampNanosecondArray, TimestampSecondArray, timezone::Tz,
+};
+use arrow_schema::{DataType, Field, Fields, Schema, TimeUnit};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+
+use crate::{properties::split_column_spec, schema::parse_time_unit};
+
+/// Default time unit of timestamps parsed with a custom format
+pub const DEFAULT_FORMAT_UNIT: TimeUnit = TimeUnit::Microsecond;
+
+/// Timestamp parsing settings
+pub struct TimestampSettings {
+    /// Custom `strftime` formats of columns
+    pub formats: Vec<(String, String)>,
+    /// Units of columns holding time since the Unix epoch
+    pub epochs: Vec<(String, TimeUnit)>,
+    /// Time zone of timestamps without an offset
+    pub timezone: Option<String>,
+    /// Time unit of stored timestamps
+    pub unit: Option<TimeUnit>,
+}
+
+/// Parses a `COLUMN=FORMAT` specification, e.g. `ts=%d/%m/%Y %H:%M`
+pub fn parse_column_format(spec: &str) -> Result<(String, String)> {
+    let (column, format) = split_column_spec(spec, "FORMAT")?;
+    Ok((column, format.to_string()))
+}
+
+/// Parses a `COLUMN=UNIT` specification, e.g. `created=ms`
+pub fn parse_column_epoch(spec: &str) -> Result<(String, TimeUnit)> {
+    let (column, unit) = split_column_spec(spec, "UNIT")?;
+    Ok((column, parse_time_unit(unit)?))
+}
+
+/// Validates a time zone name or offset, e.g. `Europe/Berlin` or `+02:00`
+pub fn parse_timezone(s: &str) -> Result<String> {
+    Tz::from_str(s)?;
+    Ok(s.to_string())
+}
+
+impl TimestampSettings {
+    /// Creates timestamp settings. Returns an error if a column has both a format and an epoch.
+    pub fn new(
+        formats: Vec<(String, String)>,
+        epochs: Vec<(String, TimeUnit)>,
+        timezone: Option<String>,
+        unit: Option<TimeUnit>,
+    ) -> Result<Self> {
+        for (column, _) in &epochs {
+            if formats.iter().any(|(c, _)| c == column) {
+                return Err(anyhow!(
+                    "Column `{column}' has both a timestamp format and an epoch unit"
+                ));
+            }
+        }
+        Ok(Self {
+            formats,
+            epochs,
+            timezone,
+            unit,
+        })
+    }
+
+    /// Returns `true` if the column is parsed by us rather than by arrow-csv
+    pub fn is_custom(&self, column: &str) -> bool {
+        self.formats.iter().any(|(c, _)| c == column)
+            || self.epochs.iter().any(|(c, _)| c == column)
+    }
+
+    /// Applies the time zone and unit to timestamp columns of an inferred schema and sets
+    /// the timestamp type for columns with custom formats and epochs
+    pub fn apply(&self, schema: &mut Schema) -> Result<()> {
+        let columns = self.formats.iter().map(|(c, _)| c);
+        for column in columns.chain(self.epochs.iter().map(|(c, _)| c)) {
+            if schema.field_with_name(column).is_err() {
+                return Err(anyhow!("Column `{column}' is not found in the schema"));
+            }
+        }
+        let timezone: Option<Arc<str>> = self.timezone.as_deref().map(Into::into);
+        let new_fields: Vec<Field> = schema
+            .fields()
+            .iter()
+            .map(|field| {
+                let name = field.name();
+                let data_type = match field.data_type() {
+                    DataType::Timestamp(unit, tz) => DataType::Timestamp(
+                        self.unit.unwrap_or(*unit),
+                        tz.clone().or(timezone.clone()),
+                    ),
+                    DataType::Date32 | DataType::Date64 if self.is_custom(name) => {
+                        field.data_type().clone()
+                    }
+                    _ => {
+                        if let Some((_, unit)) = self.epochs.iter().find(|(c, _)| c == name) {
+                            DataType::Timestamp(self.unit.unwrap_or(*unit), timezone.clone())
+                        } else if self.formats.iter().any(|(c, _)| c == name) {
+                            DataType::Timestamp(
+                                self.unit.unwrap_or(DEFAULT_FORMAT_UNIT),
+                                timezone.clone(),
+                            )
+                        } else {
+                            field.data_type().clone()
+                        }
+                    }
+                };
+                field.as_ref().clone().with_data_type(data_type)
+            })
+            .collect();
+        schema.fields = Fields::from(new_fields);
+        Ok(())
+    }
+}
+
+/// Parses a timestamp with a `strftime` format. Timestamps without an offset are
+/// interpreted in the time zone.
+fn parse_timestamp(value: &str, format: &str, tz: Option<&Tz>) -> Result<DateTime<Utc>> {
+    if let Ok(datetime) = DateTime::parse_from_str(value, format) {
+        return Ok(datetime.to_utc());
+    }
+    let naive = NaiveDateTime::parse_from_str(value, format)
+        .or_else(|err| {
+            NaiveDate::parse_from_str(value, format)
+                .map(|date| date.and_time(NaiveTime::MIN))
+                .map_err(|_| err)
+        })
+        .map_err(|err| anyhow!("Can't parse `{value}' with format `{format}': {err}"))?;
+    match tz {
+        Some(tz) => tz
+            .from_local_datetime(&naive)
+            .earliest()
+            .map(|datetime| datetime.to_utc())
+            .ok_or_else(|| anyhow!("`{value}' doesn't exist in the time zone")),
+        None => Ok(naive.and_utc()),
+    }
+}
+
+/// Converts a datetime to a number of units since the epoch
+fn to_unit(datetime: DateTime<Utc>, unit: TimeUnit) -> Result<i64> {
+    match unit {
+        TimeUnit::Second => Ok(datetime.timestamp()),
+        TimeUnit::Millisecond => Ok(datetime.timestamp_millis()),
+        TimeUnit::Microsecond => Ok(datetime.timestamp_micros()),
+        TimeUnit::Nanosecond => datetime
+            .timestamp_nanos_opt()
+            .ok_or_else(|| anyhow!("{datetime} would overflow 64-bit signed nanoseconds")),
+    }
+}
+
+/// Number of units in a second
+fn units_per_second(unit: TimeUnit) -> i64 {
+    match unit {
+        TimeUnit::Second => 1,
+        TimeUnit::Millisecond => 1_000,
+        TimeUnit::Microsecond => 1_000_000,
+        TimeUnit::Nanosecond => 1_000_000_000,
+    }
+}
+
+/// Converts time since the epoch from one unit to another
+fn parse_epoch(value: &str, from: TimeUnit, to: TimeUnit) -> Result<i64> {
+    let (from, to) = (units_per_second(from), units_per_second(to));
+    let overflow = || anyhow!("Epoch `{value}' is out of range");
+    if let Ok(value) = value.parse::<i64>() {
+        if to >= from {
+            value.checked_mul(to / from).ok_or_else(overflow)
+        } else {
+            Ok(value.div_euclid(from / to))
+        }
+    } else {
+        let value = value
+            .parse::<f64>()
+            .map_err(|_| anyhow!("Can't parse epoch `{value}'"))?;
+        let value = (value * to as f64 / from as f64).round();
+        if value.is_finite() && value.abs() < i64::MAX as f64 {
+            Ok(value as i64)
+        } else {
+            Err(overflow())
+        }
+    }
+}
+
+/// Builds a timestamp array
+fn timestamp_array(values: Vec<Option<i64>>, unit: TimeUnit, tz: Option<Arc<str>>) -> ArrayRef {
+    match unit {
+        TimeUnit::Second => Arc::new(TimestampSecondArray::from(values).with_timezone_opt(tz)),
+        TimeUnit::Millisecond => {
+            Arc::new(TimestampMillisecondArray::from(values).with_timezone_opt(tz))
+        }
+        TimeUnit::Microsecond => {
+            Arc::new(TimestampMicrosecondArray::from(values).with_timezone_opt(tz))
+        }
+        TimeUnit::Nanosecond => {
+            Arc::new(TimestampNanosecondArray::from(values).with_timezone_opt(tz))
+        }
+    }
+}
+
+/// Converts a string column to timestamps or dates using a `strftime` format
+pub fn convert_format_column(
+    values: &StringArray,
+    field: &Field,
+    format: &str,
+) -> Result<ArrayRef> {
+    let name = field.name();
+    let values = values.iter().map(|value| value.map(str::trim));
+    match field.data_type() {
+        DataType::Timestamp(unit, tz) => {
+            let timezone = tz.as_deref().map(Tz::from_str).transpose()?;
+            let values = values
+                .map(|value| {
+                    value
+                        .map(|value| {
+                            to_unit(parse_timestamp(value, format, timezone.as_ref())?, *unit)
+                        })
+                        .transpose()
+                        .map_err(|err| anyhow!("{err} (column `{name}')"))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            Ok(timestamp_array(values, *unit, tz.clone()))
+        }
+        DataType::Date32 | DataType::Date64 => {
+            let days = values
+                .map(|value| {
+                    value
+                        .map(|value| {
+                            let datetime = parse_timestamp(value, format, None)?;
+                            Ok(datetime
+                                .date_naive()
+                                .signed_duration_since(NaiveDate::default())
+                                .num_days())
+                        })
+                        .transpose()
+                        .map_err(|err: anyhow::Error| anyhow!("{err} (column `{name}')"))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            if field.data_type() == &DataType::Date32 {
+                let days = days.into_iter().map(|d| d.map(|d| d as i32));
+                Ok(Arc::new(Date32Array::from_iter(days)))
+            } else {
+                let millis = days.into_iter().map(|d| d.map(|d| d * 86_400_000));
+                Ok(Arc::new(Date64Array::from_iter(millis)))
+            }
+        }
+        data_type => Err(anyhow!(
+            "Column `{name}' of type {data_type} can't be parsed with a format"
+        )),
+    }
+}
+
+/// Converts a string column holding time since the epoch to timestamps
+pub fn convert_epoch_column(
+    values: &StringArray,
+    field: &Field,
+    epoch: TimeUnit,
+) -> Result<ArrayRef> {
+    let name = field.name();
+    match field.data_type() {
+        DataType::Timestamp(unit, tz) => {
+            let values = values
+                .iter()
+                .map(|value| {
+                    value
+                        .map(|value| parse_epoch(value.trim(), epoch, *unit))
+                        .transpose()
+                        .map_err(|err| anyhow!("{err} (column `{name}')"))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            Ok(timestamp_array(values, *unit, tz.clone()))
+        }
+        data_type => Err(anyhow!(
+            "Column `{name}' of type {data_type} can't hold epoch timestamps"
+        )),
+    }
+}
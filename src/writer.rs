@@ -0,0 +1,274 @@
+use std::{
+    io::Write,
+    sync::{Arc, mpsc},
+    thread::{self, JoinHandle},
+};
+
+use arrow_array::RecordBatch;
+use arrow_schema::SchemaRef;
+use parquet::{
+    arrow::{
+        ArrowSchemaConverter, add_encoded_arrow_schema_to_metadata,
+        arrow_writer::{
+            ArrowColumnChunk, ArrowColumnWriter, ArrowLeafColumn, compute_leaves,
+            get_column_writers,
+        },
+    },
+    errors::Result,
+    file::{
+        properties::{WriterProperties, WriterPropertiesPtr},
+        writer::SerializedFileWriter,
+    },
+    format::FileMetaData,
+};
+
+/// Thread encoding a part of the columns of the row group in progress
+struct ColumnWorker {
+    /// Number of the encoded columns
+    columns: usize,
+    sender: mpsc::SyncSender<Vec<ArrowLeafColumn>>,
+    handle: JoinHandle<Result<Vec<ArrowColumnChunk>>>,
+}
+
+impl ColumnWorker {
+    /// Starts a thread encoding columns with the writers
+    fn spawn(mut writers: Vec<ArrowColumnWriter>) -> Self {
+        let (sender, receiver) = mpsc::sync_channel::<Vec<ArrowLeafColumn>>(1);
+        let columns = writers.len();
+        let handle = thread::spawn(move || {
+            for leaves in receiver {
+                for (writer, leaf) in writers.iter_mut().zip(&leaves) {
+                    writer.write(leaf)?;
+                }
+            }
+            writers.into_iter().map(ArrowColumnWriter::close).collect()
+        });
+        Self {
+            columns,
+            sender,
+            handle,
+        }
+    }
+
+    /// Closes the column writers and returns the encoded columns
+    fn finish(self) -> Result<Vec<ArrowColumnChunk>> {
+        drop(self.sender);
+        self.handle.join().unwrap()
+    }
+}
+
+/// Column writers of the row group in progress
+enum Columns {
+    /// Writers used by the calling thread
+    Local(Vec<ArrowColumnWriter>),
+    /// Threads which own the writers. They live until the row group is flushed.
+    Workers(Vec<ColumnWorker>),
+}
+
+/// Parquet writer encoding columns in parallel. Unlike [`parquet::arrow::ArrowWriter`],
+/// it splits columns of every row group between threads.
+pub struct ParquetWriter<W: Write + Send> {
+    writer: SerializedFileWriter<W>,
+    schema: SchemaRef,
+    props: WriterPropertiesPtr,
+    columns: Option<Columns>,
+    buffered_rows: usize,
+    threads: usize,
+}
+
+impl<W: Write + Send> ParquetWriter<W> {
+    /// Creates a writer encoding columns with `threads` threads
+    pub fn try_new(
+        writer: W,
+        schema: SchemaRef,
+        mut props: WriterProperties,
+        threads: usize,
+    ) -> Result<Self> {
+        let parquet_schema = ArrowSchemaConverter::new()
+            .with_coerce_types(props.coerce_types())
+            .convert(&schema)?;
+        add_encoded_arrow_schema_to_metadata(&schema, &mut props);
+        let props = Arc::new(props);
+        let writer =
+            SerializedFileWriter::new(writer, parquet_schema.root_schema_ptr(), props.clone())?;
+        Ok(Self {
+            writer,
+            schema,
+            props,
+            columns: None,
+            buffered_rows: 0,
+            threads: threads.max(1),
+        })
+    }
+
+    /// Encodes a batch. Row groups are flushed when they reach the maximum row group size.
+    pub fn write(&mut self, batch: &RecordBatch) -> Result<()> {
+        let max_rows = self.props.max_row_group_size();
+        let mut offset = 0;
+        while offset < batch.num_rows() {
+            let length = (batch.num_rows() - offset).min(max_rows - self.buffered_rows);
+            self.write_columns(&batch.slice(offset, length))?;
+            offset += length;
+            self.buffered_rows += length;
+            if self.buffered_rows >= max_rows {
+                self.flush()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Encodes columns of a batch into the row group in progress
+    fn write_columns(&mut self, batch: &RecordBatch) -> Result<()> {
+        let columns = match &mut self.columns {
+            Some(columns) => columns,
+            columns => {
+                let writers =
+                    get_column_writers(self.writer.schema_descr(), &self.props, &self.schema)?;
+                columns.insert(Self::start_columns(writers, self.threads))
+            }
+        };
+        let mut leaves = vec![];
+        for (field, column) in self.schema.fields().iter().zip(batch.columns()) {
+            leaves.extend(compute_leaves(field, column)?);
+        }
+        match columns {
+            Columns::Local(writers) => {
+                for (writer, leaf) in writers.iter_mut().zip(&leaves) {
+                    writer.write(leaf)?;
+                }
+            }
+            Columns::Workers(workers) => {
+                let mut leaves = leaves.into_iter();
+                for worker in workers.iter() {
+                    let leaves = leaves.by_ref().take(worker.columns).collect();
+                    if worker.sender.send(leaves).is_err() {
+                        // The worker stopped with an error
+                        let Some(Columns::Workers(workers)) = self.columns.take() else {
+                            unreachable!();
+                        };
+                        Self::finish_workers(workers)?;
+                        unreachable!("a column worker stopped without an error");
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Splits column writers between threads
+    fn start_columns(writers: Vec<ArrowColumnWriter>, threads: usize) -> Columns {
+        let per_thread = writers.len().div_ceil(threads).max(1);
+        if threads == 1 || writers.len() <= 1 {
+            return Columns::Local(writers);
+        }
+        let mut writers = writers.into_iter();
+        let mut workers = vec![];
+        loop {
+            let part: Vec<_> = writers.by_ref().take(per_thread).collect();
+            if part.is_empty() {
+                return Columns::Workers(workers);
+            }
+            workers.push(ColumnWorker::spawn(part));
+        }
+    }
+
+    /// Waits for the workers and returns the encoded columns in order
+    fn finish_workers(workers: Vec<ColumnWorker>) -> Result<Vec<ArrowColumnChunk>> {
+        // All workers are joined even if some of them failed
+        let chunks: Vec<_> = workers.into_iter().map(ColumnWorker::finish).collect();
+        Ok(chunks
+            .into_iter()
+            .collect::<Result<Vec<_>>>()?
+            .into_iter()
+            .flatten()
+            .collect())
+    }
+
+    /// Flushes the row group in progress
+    pub fn flush(&mut self) -> Result<()> {
+        let Some(columns) = self.columns.take() else {
+            return Ok(());
+        };
+        self.buffered_rows = 0;
+        let chunks = match columns {
+            Columns::Local(writers) => writers
+                .into_iter()
+                .map(ArrowColumnWriter::close)
+                .collect::<Result<Vec<_>>>()?,
+            Columns::Workers(workers) => Self::finish_workers(workers)?,
+        };
+        let mut row_group = self.writer.next_row_group()?;
+        for chunk in chunks {
+            chunk.append_to_row_group(&mut row_group)?;
+        }
+        row_group.close()?;
+        Ok(())
+    }
+
+    /// Flushes the row group in progress and writes the footer
+    pub fn close(mut self) -> Result<FileMetaData> {
+        self.flush()?;
+        self.writer.close()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow_array::{Float64Array, Int64Array, StringArray};
+    use arrow_schema::{DataType, Field, Schema};
+
+    use super::*;
+
+    fn write_file(batches: &[RecordBatch], threads: usize) -> Vec<u8> {
+        let props = WriterProperties::builder()
+            .set_max_row_group_size(2500)
+            .build();
+        let mut data = vec![];
+        let mut writer =
+            ParquetWriter::try_new(&mut data, batches[0].schema(), props, threads).unwrap();
+        for batch in batches {
+            writer.write(batch).unwrap();
+        }
+        writer.close().unwrap();
+        data
+    }
+
+    #[test]
+    fn threads_write_the_same_file() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int64, false),
+            Field::new("name", DataType::Utf8, true),
+            Field::new("price", DataType::Float64, true),
+            Field::new("note", DataType::Utf8, true),
+        ]));
+        let batches: Vec<_> = (0..10)
+            .map(|i| {
+                let ids = i * 1000..(i + 1) * 1000;
+                RecordBatch::try_new(
+                    schema.clone(),
+                    vec![
+                        Arc::new(Int64Array::from_iter_values(ids.clone())),
+                        Arc::new(StringArray::from_iter_values(
+                            ids.clone().map(|id| format!("name {}", id % 37)),
+                        )),
+                        Arc::new(Float64Array::from_iter(
+                            ids.clone()
+                                .map(|id| (id % 5 > 0).then_some(id as f64 / 8.0)),
+                        )),
+                        Arc::new(StringArray::from_iter(
+                            ids.map(|id| (id % 3 > 0).then(|| format!("line\nbreak {id}"))),
+                        )),
+                    ],
+                )
+                .unwrap()
+            })
+            .collect();
+        let expected = write_file(&batches, 1);
+        for threads in [2, 3, 8] {
+            assert!(
+                write_file(&batches, threads) == expected,
+                "{threads} threads"
+            );
+        }
+    }
+}